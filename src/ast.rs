@@ -1,6 +1,7 @@
 use define_macro::define;
 
-use crate::token::Token;
+use crate::number::Number;
+use crate::token::{Span, Token};
 
 define! {
     enum stmt ->  exprStmt(ExprStmt)
@@ -8,13 +9,25 @@ define! {
                 | block(Vec<Stmt>)
                 | ifStmt(IfStmt)
                 | whileStmt(WhileStmt)
+                | forStmt(ForStmt)
                 | fnStmt(FnStmt)
-                | returnStmt(ReturnStmt);
+                | returnStmt(ReturnStmt)
+                | breakStmt(BreakStmt)
+                | continueStmt(ContinueStmt)
+                | tryStmt(TryStmt)
+                | throwStmt(ThrowStmt)
+                | classStmt(ClassStmt);
 
+    struct classStmt -> ident(Token), superclass(Option<Expr>), methods(Vec<FnStmt>);
     struct returnStmt -> keywowrd(Token), expr(Option<Expr>);
-    struct FnStmt -> ident(Token), params(Vec<Token>), body(Vec<Stmt>);
-    struct forStmt -> range(Expr), body(Box<Stmt>);
-    struct whileStmt -> condition(Expr), body(Box<Stmt>);
+    struct breakStmt -> keyword(Token), label(Option<Token>);
+    struct continueStmt -> keyword(Token), label(Option<Token>);
+    struct tryStmt -> keyword(Token), body(Box<Stmt>), catch_clause(Option<(Token, Box<Stmt>)>), finally_body(Option<Box<Stmt>>);
+    struct throwStmt -> keyword(Token), value(Expr);
+    struct FnStmt -> ident(Token), params(Vec<Param>), body(Vec<Stmt>);
+    struct param -> ident(Token), default(Option<Expr>), rest(bool);
+    struct forStmt -> label(Option<Token>), ident(Token), range(Expr), body(Box<Stmt>);
+    struct whileStmt -> label(Option<Token>), condition(Expr), body(Box<Stmt>), increment(Option<Expr>);
     struct ifStmt -> condition(Expr), if_branch(Box<Stmt>), else_branch(Option<Box<Stmt>>);
     struct exprStmt -> expr(Expr);
     struct letStmt -> ident(Token), initializer(Option<Expr>);
@@ -23,15 +36,78 @@ define! {
                 | unary(Unary)
                 | binary(Binary)
                 | call(Call)
-                | grouping(Box<Expr>)
-                | lit(Literal)
+                | grouping(Grouping)
+                | lit(Lit)
                 | logical(Logical)
-                | var(Token);
+                | var(Variable)
+                | get(Get)
+                | set(Set)
+                | this(Variable)
+                | superExpr(SuperExpr)
+                | list(ListExpr)
+                | index(Index)
+                | setIndex(SetIndex)
+                | map(MapExpr)
+                | lambda(Lambda)
+                | update(Update)
+                | conditional(Conditional)
+                | interpolation(Interpolation);
 
-    struct call -> callee(Box<Expr>), paren(Token), args(Vec<Expr>);
-    struct assign -> ident(Token), value(Box<Expr>);
-    struct unary -> operator(Token), right(Box<Expr>);
-    struct binary -> left(Box<Expr>), operator(Token), right(Box<Expr>);
-    struct logical -> left(Box<Expr>), operator(Token), right(Box<Expr>);
-    enum literal -> str(String) | number(f64) | bool(bool) | null;
+    struct variable -> ident(Token), local(Option<Local>), span(Span);
+    struct superExpr -> keyword(Token), method(Token), local(Option<Local>), span(Span);
+    struct get -> object(Box<Expr>), ident(Token), span(Span);
+    struct set -> object(Box<Expr>), ident(Token), value(Box<Expr>), span(Span);
+    struct call -> callee(Box<Expr>), paren(Token), args(Vec<Expr>), named(Vec<(Token, Expr)>), span(Span);
+    struct assign -> ident(Token), value(Box<Expr>), local(Option<Local>), span(Span);
+    struct unary -> operator(Token), right(Box<Expr>), span(Span);
+    struct binary -> left(Box<Expr>), operator(Token), right(Box<Expr>), span(Span);
+    struct logical -> left(Box<Expr>), operator(Token), right(Box<Expr>), span(Span);
+    struct grouping -> expr(Box<Expr>), span(Span);
+    struct listExpr -> elements(Vec<Expr>), span(Span);
+    struct mapExpr -> brace(Token), entries(Vec<(Expr, Expr)>), span(Span);
+    struct index -> object(Box<Expr>), bracket(Token), index(Box<Expr>), span(Span);
+    struct setIndex -> object(Box<Expr>), bracket(Token), index(Box<Expr>), value(Box<Expr>), span(Span);
+    struct lit -> value(Literal), span(Span);
+    struct lambda -> function(FnStmt), span(Span);
+    struct conditional -> condition(Box<Expr>), then_branch(Box<Expr>), else_branch(Box<Expr>), span(Span);
+    struct interpolation -> parts(Vec<Expr>), span(Span);
+    struct update -> target(Box<Expr>), operator(Token), value(Box<Expr>), postfix(bool), span(Span);
+    enum literal -> str(String) | number(Number) | bool(bool) | null;
+}
+
+// every expression keeps the span of the source it was parsed from
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Assign(assign) => assign.span,
+            Expr::Unary(unary) => unary.span,
+            Expr::Binary(binary) => binary.span,
+            Expr::Call(call) => call.span,
+            Expr::Grouping(grouping) => grouping.span,
+            Expr::Lit(lit) => lit.span,
+            Expr::Logical(logical) => logical.span,
+            Expr::Var(var) => var.span,
+            Expr::Get(get) => get.span,
+            Expr::Set(set) => set.span,
+            Expr::This(this) => this.span,
+            Expr::SuperExpr(super_expr) => super_expr.span,
+            Expr::List(list) => list.span,
+            Expr::Index(index) => index.span,
+            Expr::SetIndex(set_index) => set_index.span,
+            Expr::Map(map) => map.span,
+            Expr::Lambda(lambda) => lambda.span,
+            Expr::Update(update) => update.span,
+            Expr::Conditional(conditional) => conditional.span,
+            Expr::Interpolation(interpolation) => interpolation.span,
+        }
+    }
+}
+
+// Filled in by the resolver for every local variable: how many environments up
+// the chain the variable lives, and its slot in that environment.
+// 'None' means the variable is a global.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Local {
+    pub depth: usize,
+    pub slot: usize,
 }
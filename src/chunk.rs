@@ -0,0 +1,179 @@
+use crate::number::Number;
+use crate::signature::Signature;
+use crate::token::Span;
+use std::rc::Rc;
+
+// MENTAL MODEL:
+// a chunk is the compiled form of a single function body. The code is a flat
+// list of bytes: every instruction is one opcode byte followed by its operands.
+// Operands that index the constant pool take two bytes (big endian), stack
+// slots, upvalue indices and argument counts take one.
+
+macro_rules! opcodes {
+    ($($name:ident),* $(,)?) => {
+        #[repr(u8)]
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub enum OpCode {
+            $($name),*
+        }
+
+        impl OpCode {
+            const ALL: &'static [OpCode] = &[$(OpCode::$name),*];
+
+            pub fn from_byte(byte: u8) -> Option<OpCode> {
+                Self::ALL.get(byte as usize).copied()
+            }
+        }
+    };
+}
+
+opcodes! {
+    Constant,     // (constant) pushes a constant
+    Null,
+    True,
+    False,
+    Pop,
+    Pick,         // (distance) pushes a copy of the value that many slots below the top
+    Bury,         // (distance) moves the value on top that many slots down the stack
+    GetLocal,     // (slot)
+    SetLocal,     // (slot)
+    GetGlobal,    // (name constant)
+    DefineGlobal, // (name constant)
+    SetGlobal,    // (name constant)
+    GetUpvalue,   // (index)
+    SetUpvalue,   // (index)
+    GetProperty,  // (name constant) positioned at the object, the name at the property
+    SetProperty,  // (name constant) positioned like GetProperty
+    GetSuper,     // (name constant) positioned like GetProperty
+    Equal,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    FloorDivide,
+    Power,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+    Not,
+    Negate,
+    BitNot,
+    Jump,         // (offset) jumps forward
+    JumpIfFalse,  // (offset) jumps forward, leaving the condition on the stack
+    JumpIfNotNull, // (offset) jumps forward, leaving the value on the stack
+    Loop,         // (offset) jumps backwards
+    JumpIfPassed, // (parameter, offset) jumps over the default of a parameter that was passed
+    Call,         // (argument count)
+    Invoke,       // (name constant, argument count) calls a method of the receiver,
+                  // positioned like GetProperty and the argument count at the call
+    SuperInvoke,  // (name constant, argument count) calls a method of the superclass,
+                  // positioned like Invoke
+    CallNamed,    // (argument count, named count, then a name constant per named argument)
+    Closure,      // (function constant, then a (is_local, index) pair per upvalue)
+    CloseUpvalue,
+    Return,
+    Class,        // (name constant)
+    Inherit,
+    Method,       // (name constant)
+    BuildList,    // (element count, two bytes) collects the elements on top of the stack
+    BuildMap,     // (entry count, two bytes) collects the key value pairs on top of the stack
+    BuildString,  // (part count, two bytes) joins the parts on top of the stack into a string
+    GetIndex,
+    SetIndex,
+    Iter,         // turns the value on top of the stack into a list or a range to iterate
+    ForIter,      // (slot, offset) pushes the next item of the iteration starting at the
+                  // slot, or jumps forward when there are none left
+    Try,          // (offset) installs a handler, where errors raised until EndTry jump to
+    EndTry,       // removes the handler installed last
+    Throw,        // throws the value on top of the stack
+    Caught,       // turns the exception pushed by a handler into the value it carries
+    Rethrow,      // throws the exception on top of the stack again, after a finally block
+}
+
+pub enum Constant {
+    Number(Number),
+    Str(Rc<str>),
+    Function(Rc<Function>),
+}
+
+// a function as produced by the compiler. Closures over it are created at runtime
+pub struct Function {
+    pub name: String,
+    pub signature: Signature,
+    pub upvalue_count: usize,
+    pub chunk: Chunk,
+}
+
+impl Function {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            signature: Signature::default(),
+            upvalue_count: 0,
+            chunk: Chunk::new(),
+        }
+    }
+}
+
+pub struct Chunk {
+    pub code: Vec<u8>,
+    pub constants: Vec<Constant>,
+
+    // run-length encoded position table: (line, span, number of consecutive
+    // bytes produced by that piece of the source)
+    positions: Vec<(usize, Span, usize)>,
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Chunk {
+    pub fn new() -> Self {
+        Self {
+            code: Vec::new(),
+            constants: Vec::new(),
+            positions: Vec::new(),
+        }
+    }
+
+    pub fn write(&mut self, byte: u8, line: usize, span: Span) {
+        self.code.push(byte);
+
+        match self.positions.last_mut() {
+            Some((last_line, last_span, count)) if *last_line == line && *last_span == span => {
+                *count += 1
+            }
+            _ => self.positions.push((line, span, 1)),
+        }
+    }
+
+    pub fn add_constant(&mut self, constant: Constant) -> usize {
+        self.constants.push(constant);
+        self.constants.len() - 1
+    }
+
+    // line and span of the source that produced the byte at the given offset
+    pub fn position(&self, offset: usize) -> (usize, Span) {
+        let mut end = 0;
+        for (line, span, count) in &self.positions {
+            end += count;
+            if offset < end {
+                return (*line, *span);
+            }
+        }
+
+        self.positions
+            .last()
+            .map_or((0, Span::default()), |(line, span, _)| (*line, *span))
+    }
+}
@@ -0,0 +1,1134 @@
+use crate::{
+    ast::{
+        Call, ClassStmt, Conditional, Expr, FnStmt, ForStmt, Literal, Logical, ReturnStmt, Stmt,
+        TryStmt, Update,
+    },
+    chunk::{Constant, Function, OpCode},
+    error::InterpErr,
+    error::InterpErr as Ie,
+    number::Number,
+    signature::Signature,
+    token::{Span, Token, TokenKind as Tk},
+};
+use std::{collections::HashMap, rc::Rc};
+
+// MENTAL MODEL:
+// walks the (already resolved) tree and emits bytecode for the virtual machine.
+// Every function being compiled has its own state: the function itself, the
+// locals currently in scope and the upvalues it captures from the functions
+// around it. Locals live in the VM stack, so a local is just a stack slot
+// relative to the start of the function call. Top level declarations are
+// globals, looked up by name.
+
+#[derive(Clone, Copy, PartialEq)]
+enum FnKind {
+    Script,
+    Function,
+    Method,
+    Initializer,
+}
+
+struct LocalVar {
+    name: String,
+    depth: usize,
+    captured: bool,
+}
+
+struct UpvalueRef {
+    index: u8,
+    is_local: bool,
+}
+
+// the jumps of the 'break' and 'continue' statements of a loop are patched once
+// the loop is compiled. Locals deeper than 'scope_depth' are discarded by them,
+// and the try blocks entered after the first 'tries' are left
+struct LoopState {
+    label: Option<String>,
+    scope_depth: usize,
+    tries: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+struct FnState {
+    function: Function,
+    kind: FnKind,
+    locals: Vec<LocalVar>,
+    upvalues: Vec<UpvalueRef>,
+    scope_depth: usize,
+    names: HashMap<String, u16>,
+    loops: Vec<LoopState>,
+    tries: Vec<Option<Stmt>>, //the finally block of every try block being compiled
+}
+
+impl FnState {
+    fn new(name: String, kind: FnKind) -> Self {
+        // slot 0 holds the function being called, or the receiver for methods
+        let slot_zero = match kind {
+            FnKind::Method | FnKind::Initializer => "this",
+            _ => "",
+        };
+
+        Self {
+            function: Function::new(name),
+            kind,
+            locals: Vec::from([LocalVar {
+                name: slot_zero.to_string(),
+                depth: 0,
+                captured: false,
+            }]),
+            upvalues: Vec::new(),
+            scope_depth: 0,
+            names: HashMap::new(),
+            loops: Vec::new(),
+            tries: Vec::new(),
+        }
+    }
+}
+
+// every byte emitted is tagged with the line and span of the source being compiled
+pub struct Compiler {
+    states: Vec<FnState>,
+    line: usize,
+    span: Span,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self {
+            states: Vec::new(),
+            line: 1,
+            span: Span::default(),
+        }
+    }
+
+    // compiles a whole program into the implicit top level function
+    pub fn compile(&mut self, stmts: &[Stmt]) -> Result<Rc<Function>, InterpErr> {
+        self.states
+            .push(FnState::new("script".to_string(), FnKind::Script));
+
+        let result = stmts.iter().try_for_each(|stmt| self.statement(stmt));
+        self.emit_return();
+        let state = self.states.pop().unwrap();
+
+        result?;
+        Ok(Rc::new(state.function))
+    }
+
+    fn statement(&mut self, s: &Stmt) -> Result<(), InterpErr> {
+        match s {
+            Stmt::ExprStmt(expr_stmt) => {
+                self.expression(&expr_stmt.expr)?;
+                self.emit(OpCode::Pop);
+                Ok(())
+            }
+            Stmt::LetStmt(let_stmt) => {
+                self.mark(&let_stmt.ident);
+                self.declare(&let_stmt.ident)?;
+                match &let_stmt.initializer {
+                    Some(init) => self.expression(init)?,
+                    None => self.emit(OpCode::Null),
+                }
+                self.define(&let_stmt.ident)
+            }
+            Stmt::Block(block) => {
+                self.begin_scope();
+                let result = block.iter().try_for_each(|stmt| self.statement(stmt));
+                self.end_scope();
+                result
+            }
+            Stmt::IfStmt(if_stmt) => {
+                self.expression(&if_stmt.condition)?;
+                let then_jump = self.emit_jump(OpCode::JumpIfFalse);
+                self.emit(OpCode::Pop);
+                self.statement(&if_stmt.if_branch)?;
+
+                let else_jump = self.emit_jump(OpCode::Jump);
+                self.patch_jump(then_jump)?;
+                self.emit(OpCode::Pop);
+                if let Some(branch) = &if_stmt.else_branch {
+                    self.statement(branch)?;
+                }
+                self.patch_jump(else_jump)
+            }
+            Stmt::WhileStmt(while_stmt) => {
+                let loop_start = self.code_len();
+                self.expression(&while_stmt.condition)?;
+                let exit_jump = self.emit_jump(OpCode::JumpIfFalse);
+                self.emit(OpCode::Pop);
+
+                self.begin_loop(&while_stmt.label);
+                self.statement(&while_stmt.body)?;
+                let (breaks, continues) = self.end_loop();
+
+                // 'continue' still runs the increment of a desugared 'for'
+                continues.into_iter().try_for_each(|c| self.patch_jump(c))?;
+                if let Some(increment) = &while_stmt.increment {
+                    self.expression(increment)?;
+                    self.emit(OpCode::Pop);
+                }
+                self.emit_loop(loop_start)?;
+
+                self.patch_jump(exit_jump)?;
+                self.emit(OpCode::Pop);
+                breaks.into_iter().try_for_each(|b| self.patch_jump(b))
+            }
+            Stmt::ForStmt(for_stmt) => self.for_in(for_stmt),
+            Stmt::FnStmt(fn_stmt) => {
+                self.mark(&fn_stmt.ident);
+                self.declare(&fn_stmt.ident)?;
+                // a local function is ready as soon as it is declared, so it can be recursive
+                self.mark_initialized();
+                self.function(fn_stmt, FnKind::Function)?;
+                self.define(&fn_stmt.ident)
+            }
+            Stmt::ReturnStmt(return_stmt) if !self.state().tries.is_empty() => {
+                self.return_from_try(return_stmt)
+            }
+            Stmt::ReturnStmt(return_stmt) => {
+                self.mark(&return_stmt.keywowrd);
+                match &return_stmt.expr {
+                    Some(expr) => {
+                        self.expression(expr)?;
+                        self.emit(OpCode::Return);
+                    }
+                    None => self.emit_return(),
+                }
+                Ok(())
+            }
+            Stmt::BreakStmt(break_stmt) => {
+                self.mark(&break_stmt.keyword);
+                let target = self.loop_target(&break_stmt.keyword, &break_stmt.label)?;
+                let jump = self.emit_jump(OpCode::Jump);
+                self.state_mut().loops[target].breaks.push(jump);
+                Ok(())
+            }
+            Stmt::ContinueStmt(continue_stmt) => {
+                self.mark(&continue_stmt.keyword);
+                let target = self.loop_target(&continue_stmt.keyword, &continue_stmt.label)?;
+                let jump = self.emit_jump(OpCode::Jump);
+                self.state_mut().loops[target].continues.push(jump);
+                Ok(())
+            }
+            Stmt::TryStmt(try_stmt) => self.try_stmt(try_stmt),
+            Stmt::ThrowStmt(throw_stmt) => {
+                self.expression(&throw_stmt.value)?;
+                let span = throw_stmt.keyword.span.to(throw_stmt.value.span());
+                self.mark_span(throw_stmt.keyword.line, span);
+                self.emit(OpCode::Throw);
+                Ok(())
+            }
+            Stmt::ClassStmt(class_stmt) => self.class(class_stmt),
+        }
+    }
+
+    // the handler of a try block jumps to its catch block, or to a copy of its
+    // finally block that throws the exception again. Every other way out of the
+    // try (or catch) block removes the handler and runs a copy of the finally
+    // block too: at its end, and before a 'break', 'continue' or 'return'
+    fn try_stmt(&mut self, t: &TryStmt) -> Result<(), InterpErr> {
+        let finally = t.finally_body.as_deref();
+        self.mark(&t.keyword);
+
+        let handler = self.guarded(&t.body, finally)?;
+        let mut exits = vec![self.emit_jump(OpCode::Jump)];
+        self.patch_jump(handler)?;
+
+        // the exception pushed by the handler becomes the caught variable
+        let mut caught = false;
+        if let Some((ident, body)) = &t.catch_clause {
+            self.begin_scope();
+            self.mark(ident);
+            self.emit(OpCode::Caught);
+            self.declare(ident)?;
+            self.define(ident)?;
+
+            match finally {
+                Some(_) => {
+                    let handler = self.guarded(body, finally)?;
+                    self.end_scope();
+                    exits.push(self.emit_jump(OpCode::Jump));
+                    self.patch_jump(handler)?;
+                    caught = true;
+                }
+                None => {
+                    self.statement(body)?;
+                    self.end_scope();
+                }
+            }
+        }
+
+        if let Some(body) = finally {
+            // the caught variable is still below the exception thrown by the catch block
+            self.begin_scope();
+            if caught {
+                self.hidden_local(&t.keyword, " caught")?;
+            }
+            self.hidden_local(&t.keyword, " exception")?;
+            let slot = (self.state().locals.len() - 1) as u8;
+
+            self.statement(body)?;
+            self.emit(OpCode::GetLocal);
+            self.emit_byte(slot);
+            self.emit(OpCode::Rethrow);
+            // never reached, the locals are dropped while unwinding
+            self.end_scope();
+        }
+
+        exits.into_iter().try_for_each(|exit| self.patch_jump(exit))
+    }
+
+    // a block protected by a handler. Leaving it normally removes the handler
+    // and runs the finally block
+    fn guarded(&mut self, body: &Stmt, finally: Option<&Stmt>) -> Result<usize, InterpErr> {
+        let handler = self.emit_jump(OpCode::Try);
+        self.state_mut().tries.push(finally.cloned());
+        self.statement(body)?;
+        self.state_mut().tries.pop();
+
+        self.emit(OpCode::EndTry);
+        if let Some(finally) = finally {
+            self.statement(finally)?;
+        }
+        Ok(handler)
+    }
+
+    // leaves the try blocks entered after the first 'depth', innermost first.
+    // Each finally block is compiled as if the ones further in were already left
+    fn exit_tries(&mut self, depth: usize) -> Result<(), InterpErr> {
+        let mut exited = Vec::new();
+        let mut result = Ok(());
+
+        while self.state().tries.len() > depth && result.is_ok() {
+            let finally = self.state_mut().tries.pop().unwrap();
+            self.emit(OpCode::EndTry);
+            if let Some(body) = &finally {
+                result = self.statement(body);
+            }
+            exited.push(finally);
+        }
+
+        self.state_mut().tries.extend(exited.into_iter().rev());
+        result
+    }
+
+    // the value is kept in a hidden local while the finally blocks around run
+    fn return_from_try(&mut self, r: &ReturnStmt) -> Result<(), InterpErr> {
+        self.mark(&r.keywowrd);
+        self.begin_scope();
+        match &r.expr {
+            Some(expr) => self.expression(expr)?,
+            None => self.emit_implicit_value(),
+        }
+        self.hidden_local(&r.keywowrd, " return")?;
+        let slot = (self.state().locals.len() - 1) as u8;
+
+        self.exit_tries(0)?;
+        self.emit(OpCode::GetLocal);
+        self.emit_byte(slot);
+        self.emit(OpCode::Return);
+        // never reached, returning drops the locals
+        self.end_scope();
+        Ok(())
+    }
+
+    fn begin_loop(&mut self, label: &Option<Token>) {
+        let scope_depth = self.state().scope_depth;
+        let tries = self.state().tries.len();
+        self.state_mut().loops.push(LoopState {
+            label: label.as_ref().map(|label| label.lexeme.clone()),
+            scope_depth,
+            tries,
+            breaks: Vec::new(),
+            continues: Vec::new(),
+        });
+    }
+
+    // the pending 'break' and 'continue' jumps of the loop
+    fn end_loop(&mut self) -> (Vec<usize>, Vec<usize>) {
+        let state = self.state_mut().loops.pop().unwrap();
+        (state.breaks, state.continues)
+    }
+
+    // finds the loop a 'break' or 'continue' jumps out of, leaves the try blocks
+    // entered inside it and discards the locals declared inside it. They stay in
+    // the compiler state, the code after the jump still uses them. A local may be
+    // captured by a closure compiled after the jump, so all of them are closed to
+    // be safe
+    fn loop_target(&mut self, keyword: &Token, label: &Option<Token>) -> Result<usize, InterpErr> {
+        let state = self.state();
+        let target = state.loops.iter().rposition(|l| match label {
+            Some(label) => l.label.as_deref() == Some(label.lexeme.as_str()),
+            None => true,
+        });
+
+        let Some(target) = target else {
+            return compile_error(keyword, "Can't find the loop to jump out of");
+        };
+
+        self.exit_tries(state.loops[target].tries)?;
+        let state = self.state();
+        let depth = state.loops[target].scope_depth;
+        let count = state
+            .locals
+            .iter()
+            .rev()
+            .take_while(|l| l.depth > depth)
+            .count();
+        for _ in 0..count {
+            self.emit(OpCode::CloseUpvalue);
+        }
+        Ok(target)
+    }
+
+    // the iterable and the position in it live in two hidden locals. The loop
+    // variable is the only local of a scope around the body, so it is closed
+    // (and recreated) on every iteration
+    fn for_in(&mut self, f: &ForStmt) -> Result<(), InterpErr> {
+        self.begin_scope();
+
+        self.expression(&f.range)?;
+        self.mark_span(f.ident.line, f.range.span());
+        self.emit(OpCode::Iter);
+        self.hidden_local(&f.ident, " iterable")?;
+        self.literal(&Literal::Number(Number::Int(0)))?;
+        self.hidden_local(&f.ident, " index")?;
+        let slot = (self.state().locals.len() - 2) as u8;
+
+        let loop_start = self.code_len();
+        self.emit(OpCode::ForIter);
+        self.emit_byte(slot);
+        let exit_jump = self.code_len();
+        self.emit_byte(0xff);
+        self.emit_byte(0xff);
+
+        self.begin_loop(&f.label);
+        self.begin_scope();
+        self.declare(&f.ident)?;
+        self.define(&f.ident)?;
+        self.statement(&f.body)?;
+        self.end_scope();
+        let (breaks, continues) = self.end_loop();
+
+        continues.into_iter().try_for_each(|c| self.patch_jump(c))?;
+        self.emit_loop(loop_start)?;
+
+        self.patch_jump(exit_jump)?;
+        breaks.into_iter().try_for_each(|b| self.patch_jump(b))?;
+        self.end_scope();
+        Ok(())
+    }
+
+    // a local holding the value on top of the stack, whose name can't clash
+    // with any variable
+    fn hidden_local(&mut self, at: &Token, name: &str) -> Result<(), InterpErr> {
+        let mut hidden = at.clone();
+        hidden.lexeme = name.to_string();
+        self.declare(&hidden)?;
+        self.define(&hidden)
+    }
+
+    fn class(&mut self, c: &ClassStmt) -> Result<(), InterpErr> {
+        self.mark(&c.ident);
+        let name = self.name_constant(&c.ident.lexeme)?;
+        self.declare(&c.ident)?;
+        self.emit_with_constant(OpCode::Class, name);
+        self.define(&c.ident)?;
+
+        // 'super' is a local holding the superclass in a scope around the methods
+        if let Some(superclass) = &c.superclass {
+            self.expression(superclass)?;
+            self.begin_scope();
+            self.add_local("super");
+            self.mark_initialized();
+
+            self.named_variable(&c.ident, false)?;
+            self.mark_span(c.ident.line, superclass.span());
+            self.emit(OpCode::Inherit);
+        }
+
+        // the class stays on the stack while its methods are attached to it
+        self.named_variable(&c.ident, false)?;
+        for method in &c.methods {
+            let kind = if method.ident.lexeme == "init" {
+                FnKind::Initializer
+            } else {
+                FnKind::Method
+            };
+            let name = self.name_constant(&method.ident.lexeme)?;
+            self.function(method, kind)?;
+            self.emit_with_constant(OpCode::Method, name);
+        }
+        self.emit(OpCode::Pop);
+
+        if c.superclass.is_some() {
+            self.end_scope();
+        }
+
+        Ok(())
+    }
+
+    // compiles the function body into its own chunk and emits the code that
+    // creates a closure over it in the enclosing function
+    fn function(&mut self, f: &FnStmt, kind: FnKind) -> Result<(), InterpErr> {
+        let mut state = FnState::new(f.ident.lexeme.clone(), kind);
+        state.function.signature = Signature::of(f);
+        self.states.push(state);
+        self.begin_scope();
+
+        //defaults see the parameters before them
+        let result = f
+            .params
+            .iter()
+            .enumerate()
+            .try_for_each(|(i, param)| {
+                self.declare(&param.ident)?;
+                if let Some(default) = &param.default {
+                    self.default(i, default)?;
+                }
+                self.define(&param.ident)
+            })
+            .and_then(|_| f.body.iter().try_for_each(|stmt| self.statement(stmt)));
+        self.emit_return();
+
+        let state = self.states.pop().unwrap();
+        result?;
+
+        let mut function = state.function;
+        function.upvalue_count = state.upvalues.len();
+        let constant = self.make_constant(Constant::Function(Rc::new(function)))?;
+        self.emit_with_constant(OpCode::Closure, constant);
+
+        for upvalue in &state.upvalues {
+            self.emit_byte(upvalue.is_local as u8);
+            self.emit_byte(upvalue.index);
+        }
+
+        Ok(())
+    }
+
+    // the parameter was already declared: its slot gets the default when the
+    // caller left it out
+    fn default(&mut self, param: usize, default: &Expr) -> Result<(), InterpErr> {
+        let slot = (self.state().locals.len() - 1) as u8;
+        self.emit(OpCode::JumpIfPassed);
+        self.emit_byte(param as u8);
+        let jump = self.code_len();
+        self.emit_byte(0xff);
+        self.emit_byte(0xff);
+
+        self.expression(default)?;
+        self.emit(OpCode::SetLocal);
+        self.emit_byte(slot);
+        self.emit(OpCode::Pop);
+        self.patch_jump(jump)
+    }
+
+    fn expression(&mut self, e: &Expr) -> Result<(), InterpErr> {
+        match e {
+            Expr::Assign(assign) => {
+                self.expression(&assign.value)?;
+                self.named_variable(&assign.ident, true)
+            }
+            Expr::Unary(unary) => {
+                self.expression(&unary.right)?;
+                self.mark_span(unary.operator.line, unary.span);
+                match unary.operator.kind {
+                    Tk::Bang => self.emit(OpCode::Not),
+                    Tk::Tilde => self.emit(OpCode::BitNot),
+                    _ => self.emit(OpCode::Negate),
+                }
+                Ok(())
+            }
+            Expr::Binary(binary) => {
+                self.expression(&binary.left)?;
+                self.expression(&binary.right)?;
+                self.mark_span(binary.operator.line, binary.span);
+                self.binary_op(&binary.operator)
+            }
+            Expr::Call(call) => self.call(call),
+            Expr::Grouping(grouping) => self.expression(&grouping.expr),
+            Expr::Lit(lit) => self.literal(&lit.value),
+            Expr::Logical(logical) => self.logical(logical),
+            Expr::Var(var) => self.named_variable(&var.ident, false),
+            Expr::Get(get) => {
+                self.expression(&get.object)?;
+                self.emit_property(OpCode::GetProperty, get.object.span(), &get.ident)
+            }
+            Expr::Set(set) => {
+                self.expression(&set.object)?;
+                self.expression(&set.value)?;
+                self.emit_property(OpCode::SetProperty, set.object.span(), &set.ident)
+            }
+            Expr::This(this) => self.named_variable(&this.ident, false),
+            Expr::SuperExpr(super_expr) => {
+                self.this(&super_expr.keyword)?;
+                self.named_variable(&super_expr.keyword, false)?;
+                self.emit_property(OpCode::GetSuper, super_expr.span, &super_expr.method)
+            }
+            Expr::List(list) => {
+                for element in &list.elements {
+                    self.expression(element)?;
+                }
+
+                self.mark_span(self.line, list.span);
+                self.emit_collection(OpCode::BuildList, list.elements.len(), "list")
+            }
+            Expr::Map(map) => {
+                for (key, value) in &map.entries {
+                    self.expression(key)?;
+                    self.expression(value)?;
+                }
+
+                self.mark_span(map.brace.line, map.span);
+                self.emit_collection(OpCode::BuildMap, map.entries.len(), "map")
+            }
+            Expr::Lambda(lambda) => self.function(&lambda.function, FnKind::Function),
+            // index errors point at the index expression
+            Expr::Index(index) => {
+                self.expression(&index.object)?;
+                self.expression(&index.index)?;
+                self.mark_span(index.bracket.line, index.index.span());
+                self.emit(OpCode::GetIndex);
+                Ok(())
+            }
+            Expr::SetIndex(set_index) => {
+                self.expression(&set_index.object)?;
+                self.expression(&set_index.index)?;
+                self.expression(&set_index.value)?;
+                self.mark_span(set_index.bracket.line, set_index.index.span());
+                self.emit(OpCode::SetIndex);
+                Ok(())
+            }
+            Expr::Update(update) => self.update(update),
+            Expr::Conditional(conditional) => self.conditional(conditional),
+            Expr::Interpolation(interpolation) => {
+                for part in &interpolation.parts {
+                    self.expression(part)?;
+                }
+
+                let count = interpolation.parts.len();
+                self.mark_span(self.line, interpolation.span);
+                self.emit_collection(OpCode::BuildString, count, "interpolated string")
+            }
+        }
+    }
+
+    fn binary_op(&mut self, operator: &Token) -> Result<(), InterpErr> {
+        match operator.kind {
+            Tk::Plus => self.emit(OpCode::Add),
+            Tk::Minus => self.emit(OpCode::Subtract),
+            Tk::Star => self.emit(OpCode::Multiply),
+            Tk::Slash => self.emit(OpCode::Divide),
+            Tk::Percent => self.emit(OpCode::Modulo),
+            Tk::SlashSlash => self.emit(OpCode::FloorDivide),
+            Tk::StarStar => self.emit(OpCode::Power),
+            Tk::Ampersand => self.emit(OpCode::BitAnd),
+            Tk::Pipe => self.emit(OpCode::BitOr),
+            Tk::Caret => self.emit(OpCode::BitXor),
+            Tk::LessLess => self.emit(OpCode::ShiftLeft),
+            Tk::GreaterGreater => self.emit(OpCode::ShiftRight),
+            Tk::Greater => self.emit(OpCode::Greater),
+            Tk::GreaterEqual => self.emit(OpCode::GreaterEqual),
+            Tk::Less => self.emit(OpCode::Less),
+            Tk::LessEqual => self.emit(OpCode::LessEqual),
+            Tk::EqualEqual => self.emit(OpCode::Equal),
+            Tk::BangEqual => {
+                self.emit(OpCode::Equal);
+                self.emit(OpCode::Not);
+            }
+            _ => return compile_error(operator, "Invalid operator"),
+        }
+        Ok(())
+    }
+
+    // the object (and index) of the target stay on the stack for the store,
+    // a postfix update buries a copy of the old value below them as its result
+    fn update(&mut self, u: &Update) -> Result<(), InterpErr> {
+        let operands = match &*u.target {
+            Expr::Var(var) => {
+                self.named_variable(&var.ident, false)?;
+                0
+            }
+            Expr::Get(get) => {
+                self.expression(&get.object)?;
+                self.mark_span(get.ident.line, get.span);
+                self.emit(OpCode::Pick);
+                self.emit_byte(0);
+                self.emit_property(OpCode::GetProperty, get.object.span(), &get.ident)?;
+                1
+            }
+            Expr::Index(index) => {
+                self.expression(&index.object)?;
+                self.expression(&index.index)?;
+                self.mark_span(index.bracket.line, index.index.span());
+                for _ in 0..2 {
+                    self.emit(OpCode::Pick);
+                    self.emit_byte(1);
+                }
+                self.emit(OpCode::GetIndex);
+                2
+            }
+            _ => unreachable!("the parser only updates variables, properties and elements"),
+        };
+
+        if u.postfix {
+            self.emit(OpCode::Pick);
+            self.emit_byte(0);
+            self.emit(OpCode::Bury);
+            self.emit_byte(operands + 1);
+        }
+
+        self.expression(&u.value)?;
+        self.mark_span(u.operator.line, u.span);
+        self.binary_op(&u.operator)?;
+
+        match &*u.target {
+            Expr::Var(var) => self.named_variable(&var.ident, true)?,
+            Expr::Get(get) => {
+                self.emit_property(OpCode::SetProperty, get.object.span(), &get.ident)?
+            }
+            _ => self.emit(OpCode::SetIndex),
+        }
+
+        if u.postfix {
+            self.emit(OpCode::Pop);
+        }
+        Ok(())
+    }
+
+    // method calls skip creating a bound method when the callee is a property
+    // access. Calls with named arguments always go through the callee value
+    fn call(&mut self, c: &Call) -> Result<(), InterpErr> {
+        if c.args.len() + c.named.len() > u8::MAX as usize {
+            return compile_error(&c.paren, "Can't have more than 255 arguments");
+        }
+
+        // the receiver goes below the arguments, the superclass above them
+        match &*c.callee {
+            _ if !c.named.is_empty() => self.expression(&c.callee)?,
+            Expr::Get(get) => self.expression(&get.object)?,
+            Expr::SuperExpr(super_expr) => self.this(&super_expr.keyword)?,
+            callee => self.expression(callee)?,
+        }
+
+        for arg in c.args.iter().chain(c.named.iter().map(|(_, arg)| arg)) {
+            self.expression(arg)?;
+        }
+
+        let op = match &*c.callee {
+            _ if !c.named.is_empty() => None,
+            Expr::Get(get) => Some((OpCode::Invoke, get.object.span(), &get.ident)),
+            Expr::SuperExpr(super_expr) => {
+                self.named_variable(&super_expr.keyword, false)?;
+                Some((OpCode::SuperInvoke, super_expr.span, &super_expr.method))
+            }
+            _ => None,
+        };
+
+        self.mark_span(c.paren.line, c.span);
+        match op {
+            Some((op, object, method)) => {
+                self.emit_property(op, object, method)?;
+                self.mark_span(c.paren.line, c.span);
+            }
+            None if !c.named.is_empty() => {
+                let mut names = Vec::new();
+                for (ident, _) in &c.named {
+                    names.push(self.name_constant(&ident.lexeme)?);
+                }
+                self.emit(OpCode::CallNamed);
+                self.emit_byte(c.args.len() as u8);
+                self.emit_byte(c.named.len() as u8);
+                for name in names {
+                    self.emit_byte_pair(name);
+                }
+                return Ok(());
+            }
+            None => self.emit(OpCode::Call),
+        }
+        self.emit_byte(c.args.len() as u8);
+        Ok(())
+    }
+
+    // loads the receiver of a 'super' access, using the position of the 'super' keyword
+    fn this(&mut self, keyword: &Token) -> Result<(), InterpErr> {
+        let mut this = keyword.clone();
+        this.lexeme = "this".to_string();
+        self.named_variable(&this, false)
+    }
+
+    fn literal(&mut self, l: &Literal) -> Result<(), InterpErr> {
+        match l {
+            Literal::Str(s) => {
+                let constant = self.make_constant(Constant::Str(Rc::from(s.as_str())))?;
+                self.emit_with_constant(OpCode::Constant, constant);
+            }
+            Literal::Number(n) => {
+                let constant = self.make_constant(Constant::Number(*n))?;
+                self.emit_with_constant(OpCode::Constant, constant);
+            }
+            Literal::Bool(true) => self.emit(OpCode::True),
+            Literal::Bool(false) => self.emit(OpCode::False),
+            Literal::Null => self.emit(OpCode::Null),
+        }
+        Ok(())
+    }
+
+    // both operators leave the value that decided the result on the stack
+    fn logical(&mut self, l: &Logical) -> Result<(), InterpErr> {
+        self.expression(&l.left)?;
+
+        if let Tk::Or = l.operator.kind {
+            let else_jump = self.emit_jump(OpCode::JumpIfFalse);
+            let end_jump = self.emit_jump(OpCode::Jump);
+            self.patch_jump(else_jump)?;
+            self.emit(OpCode::Pop);
+            self.expression(&l.right)?;
+            self.patch_jump(end_jump)
+        } else if let Tk::QuestionQuestion = l.operator.kind {
+            let end_jump = self.emit_jump(OpCode::JumpIfNotNull);
+            self.emit(OpCode::Pop);
+            self.expression(&l.right)?;
+            self.patch_jump(end_jump)
+        } else {
+            let end_jump = self.emit_jump(OpCode::JumpIfFalse);
+            self.emit(OpCode::Pop);
+            self.expression(&l.right)?;
+            self.patch_jump(end_jump)
+        }
+    }
+
+    fn conditional(&mut self, c: &Conditional) -> Result<(), InterpErr> {
+        self.expression(&c.condition)?;
+        let else_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit(OpCode::Pop);
+        self.expression(&c.then_branch)?;
+        let end_jump = self.emit_jump(OpCode::Jump);
+
+        self.patch_jump(else_jump)?;
+        self.emit(OpCode::Pop);
+        self.expression(&c.else_branch)?;
+        self.patch_jump(end_jump)
+    }
+
+    fn named_variable(&mut self, ident: &Token, assign: bool) -> Result<(), InterpErr> {
+        self.mark(ident);
+        let depth = self.states.len() - 1;
+
+        let (op, operand) = if let Some(slot) = self.resolve_local(depth, &ident.lexeme) {
+            let op = if assign {
+                OpCode::SetLocal
+            } else {
+                OpCode::GetLocal
+            };
+            (op, slot)
+        } else if let Some(index) = self.resolve_upvalue(depth, ident)? {
+            let op = if assign {
+                OpCode::SetUpvalue
+            } else {
+                OpCode::GetUpvalue
+            };
+            (op, index)
+        } else {
+            let op = if assign {
+                OpCode::SetGlobal
+            } else {
+                OpCode::GetGlobal
+            };
+            let name = self.name_constant(&ident.lexeme)?;
+            self.emit_with_constant(op, name);
+            return Ok(());
+        };
+
+        self.emit(op);
+        self.emit_byte(operand);
+        Ok(())
+    }
+
+    fn resolve_local(&self, depth: usize, name: &str) -> Option<u8> {
+        self.states[depth]
+            .locals
+            .iter()
+            .rposition(|local| local.name == name)
+            .map(|slot| slot as u8)
+    }
+
+    // looks for the variable in the enclosing functions, threading an upvalue
+    // through every function in between
+    fn resolve_upvalue(&mut self, depth: usize, ident: &Token) -> Result<Option<u8>, InterpErr> {
+        if depth == 0 {
+            return Ok(None);
+        }
+
+        if let Some(slot) = self.resolve_local(depth - 1, &ident.lexeme) {
+            self.states[depth - 1].locals[slot as usize].captured = true;
+            return self.add_upvalue(depth, ident, slot, true).map(Some);
+        }
+
+        match self.resolve_upvalue(depth - 1, ident)? {
+            Some(index) => self.add_upvalue(depth, ident, index, false).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn add_upvalue(
+        &mut self,
+        depth: usize,
+        ident: &Token,
+        index: u8,
+        is_local: bool,
+    ) -> Result<u8, InterpErr> {
+        let upvalues = &mut self.states[depth].upvalues;
+
+        if let Some(existing) = upvalues
+            .iter()
+            .position(|u| u.index == index && u.is_local == is_local)
+        {
+            return Ok(existing as u8);
+        }
+
+        if upvalues.len() > u8::MAX as usize {
+            return compile_error(ident, "Too many closure variables in function");
+        }
+
+        upvalues.push(UpvalueRef { index, is_local });
+        Ok((upvalues.len() - 1) as u8)
+    }
+
+    fn declare(&mut self, ident: &Token) -> Result<(), InterpErr> {
+        if self.state().scope_depth == 0 {
+            return Ok(());
+        }
+
+        if self.state().locals.len() > u8::MAX as usize {
+            return compile_error(ident, "Too many local variables in function");
+        }
+
+        self.add_local(&ident.lexeme);
+        Ok(())
+    }
+
+    // the value is already on top of the stack: locals just stay there
+    fn define(&mut self, ident: &Token) -> Result<(), InterpErr> {
+        if self.state().scope_depth > 0 {
+            self.mark_initialized();
+            return Ok(());
+        }
+
+        let name = self.name_constant(&ident.lexeme)?;
+        self.emit_with_constant(OpCode::DefineGlobal, name);
+        Ok(())
+    }
+
+    // the scope depth of the local is only set once it is initialized
+    fn add_local(&mut self, name: &str) {
+        self.state_mut().locals.push(LocalVar {
+            name: name.to_string(),
+            depth: usize::MAX,
+            captured: false,
+        });
+    }
+
+    fn mark_initialized(&mut self) {
+        let state = self.state_mut();
+        if state.scope_depth == 0 {
+            return;
+        }
+
+        let depth = state.scope_depth;
+        if let Some(local) = state.locals.last_mut() {
+            local.depth = depth;
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.state_mut().scope_depth += 1;
+    }
+
+    // discards the locals of the scope. Captured ones are moved to the heap
+    fn end_scope(&mut self) {
+        self.state_mut().scope_depth -= 1;
+
+        loop {
+            let state = self.state();
+            let captured = match state.locals.last() {
+                Some(local) if local.depth > state.scope_depth => local.captured,
+                _ => break,
+            };
+
+            if captured {
+                self.emit(OpCode::CloseUpvalue);
+            } else {
+                self.emit(OpCode::Pop);
+            }
+            self.state_mut().locals.pop();
+        }
+    }
+
+    fn name_constant(&mut self, name: &str) -> Result<u16, InterpErr> {
+        if let Some(&constant) = self.state().names.get(name) {
+            return Ok(constant);
+        }
+
+        let constant = self.make_constant(Constant::Str(Rc::from(name)))?;
+        self.state_mut().names.insert(name.to_string(), constant);
+        Ok(constant)
+    }
+
+    fn make_constant(&mut self, constant: Constant) -> Result<u16, InterpErr> {
+        let index = self.state_mut().function.chunk.add_constant(constant);
+        if index > u16::MAX as usize {
+            return Err(Ie::SyntaxError {
+                line: self.line,
+                span: self.span,
+                msg: "Too many constants in one chunk".to_string(),
+                place: "".to_string(),
+            });
+        }
+
+        Ok(index as u16)
+    }
+
+    fn emit_return(&mut self) {
+        self.emit_implicit_value();
+        self.emit(OpCode::Return);
+    }
+
+    // the value returned by a bare 'return'
+    fn emit_implicit_value(&mut self) {
+        if let FnKind::Initializer = self.state().kind {
+            self.emit(OpCode::GetLocal);
+            self.emit_byte(0);
+        } else {
+            self.emit(OpCode::Null);
+        }
+    }
+
+    fn emit_jump(&mut self, op: OpCode) -> usize {
+        self.emit(op);
+        self.emit_byte(0xff);
+        self.emit_byte(0xff);
+        self.code_len() - 2
+    }
+
+    // the jump offset is only known once the code it jumps over was emitted
+    fn patch_jump(&mut self, offset: usize) -> Result<(), InterpErr> {
+        let jump = self.code_len() - offset - 2;
+        if jump > u16::MAX as usize {
+            return self.too_far();
+        }
+
+        let code = &mut self.state_mut().function.chunk.code;
+        code[offset..offset + 2].copy_from_slice(&(jump as u16).to_be_bytes());
+        Ok(())
+    }
+
+    fn emit_loop(&mut self, loop_start: usize) -> Result<(), InterpErr> {
+        self.emit(OpCode::Loop);
+
+        let offset = self.code_len() - loop_start + 2;
+        if offset > u16::MAX as usize {
+            return self.too_far();
+        }
+
+        for byte in (offset as u16).to_be_bytes() {
+            self.emit_byte(byte);
+        }
+        Ok(())
+    }
+
+    fn too_far(&self) -> Result<(), InterpErr> {
+        Err(Ie::SyntaxError {
+            line: self.line,
+            span: self.span,
+            msg: "Too much code to jump over".to_string(),
+            place: "".to_string(),
+        })
+    }
+
+    // collections are built from the values on top of the stack
+    fn emit_collection(&mut self, op: OpCode, count: usize, kind: &str) -> Result<(), InterpErr> {
+        let Ok(count) = u16::try_from(count) else {
+            return Err(Ie::SyntaxError {
+                line: self.line,
+                span: self.span,
+                msg: format!("Too many entries in {kind} literal"),
+                place: String::new(),
+            });
+        };
+
+        self.emit(op);
+        for byte in count.to_be_bytes() {
+            self.emit_byte(byte);
+        }
+        Ok(())
+    }
+
+    fn emit_with_constant(&mut self, op: OpCode, constant: u16) {
+        self.emit(op);
+        self.emit_byte_pair(constant);
+    }
+
+    // the opcode is positioned at the object and the name at the property, so
+    // errors about either can point at it
+    fn emit_property(&mut self, op: OpCode, object: Span, ident: &Token) -> Result<(), InterpErr> {
+        let name = self.name_constant(&ident.lexeme)?;
+        self.mark_span(ident.line, object);
+        self.emit(op);
+        self.mark(ident);
+        self.emit_byte_pair(name);
+        Ok(())
+    }
+
+    fn emit_byte_pair(&mut self, value: u16) {
+        for byte in value.to_be_bytes() {
+            self.emit_byte(byte);
+        }
+    }
+
+    fn emit(&mut self, op: OpCode) {
+        self.emit_byte(op as u8);
+    }
+
+    fn emit_byte(&mut self, byte: u8) {
+        let (line, span) = (self.line, self.span);
+        self.state_mut().function.chunk.write(byte, line, span);
+    }
+
+    fn mark(&mut self, token: &Token) {
+        self.mark_span(token.line, token.span);
+    }
+
+    fn mark_span(&mut self, line: usize, span: Span) {
+        self.line = line;
+        self.span = span;
+    }
+
+    fn code_len(&self) -> usize {
+        self.state().function.chunk.code.len()
+    }
+
+    fn state(&self) -> &FnState {
+        self.states.last().unwrap()
+    }
+
+    fn state_mut(&mut self) -> &mut FnState {
+        self.states.last_mut().unwrap()
+    }
+}
+
+fn compile_error<T>(token: &Token, msg: &str) -> Result<T, InterpErr> {
+    Err(Ie::SyntaxError {
+        line: token.line,
+        span: token.span,
+        msg: msg.to_string(),
+        place: token.lexeme.clone(),
+    })
+}
@@ -0,0 +1,220 @@
+use crate::{
+    error::{InterpErr, TraceFrame},
+    token::Span,
+};
+use format as fmt;
+use std::io::IsTerminal;
+
+// MENTAL MODEL:
+// turns an error into a report pointing at the source it came from:
+//
+//   runtime error: Undefined variable 'x'
+//    --> script.lox:2:9
+//     |
+//   2 | let b = x + 1;
+//     |         ^
+//     = help: variables must be declared with 'let' before they are used
+//   traceback (most recent call first):
+//     line 2, in <fn f>
+//     line 5, in <script>
+//
+// the location is computed from the span of the error, so it always agrees with
+// the snippet. Colors are only used when the output is a terminal.
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+pub struct Diagnostic<'a> {
+    file: &'a str,
+    source: &'a str,
+    color: bool,
+}
+
+impl<'a> Diagnostic<'a> {
+    // colored only if stderr, where the reports are printed, is a terminal
+    pub fn new(file: &'a str, source: &'a str) -> Self {
+        Self {
+            file,
+            source,
+            color: std::io::stderr().is_terminal(),
+        }
+    }
+
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn render(&self, err: &InterpErr) -> String {
+        let (kind, line, span, msg, trace) = match err {
+            InterpErr::SyntaxError {
+                line, span, msg, ..
+            } => ("syntax error", *line, *span, msg.clone(), &[][..]),
+            InterpErr::RuntimeError {
+                line,
+                span,
+                msg,
+                trace,
+            } => ("runtime error", *line, *span, msg.clone(), &trace[..]),
+            InterpErr::Thrown {
+                line,
+                span,
+                value,
+                trace,
+            } => (
+                "uncaught exception",
+                *line,
+                *span,
+                value.to_string(),
+                &trace[..],
+            ),
+        };
+
+        let mut out = fmt!("{}: {}\n", self.paint(RED, kind), self.paint(BOLD, &msg));
+        out += &self.location(line, span, &msg);
+        out += &self.traceback(line, trace);
+        out
+    }
+
+    fn location(&self, line: usize, span: Span, msg: &str) -> String {
+        // errors raised by code from an earlier input of the REPL point outside the
+        // current source, only their line is known then
+        let Some(snippet) = self.snippet(span) else {
+            return fmt!("{} {}:{line}\n", self.paint(BLUE, "-->"), self.file);
+        };
+
+        let number = snippet.line.to_string();
+        let pad = " ".repeat(number.len());
+        let gutter = self.paint(BLUE, "|");
+
+        let mut out = fmt!(
+            "{pad}{} {}:{}:{}\n",
+            self.paint(BLUE, "-->"),
+            self.file,
+            snippet.line,
+            snippet.column
+        );
+        out += &fmt!("{pad} {gutter}\n");
+        out += &fmt!("{} {gutter} {}\n", self.paint(BLUE, &number), snippet.text);
+        out += &fmt!(
+            "{pad} {gutter} {}{}\n",
+            " ".repeat(snippet.column - 1),
+            self.paint(RED, &"^".repeat(snippet.width))
+        );
+
+        if let Some(help) = help(msg) {
+            out += &fmt!(
+                "{pad} {} {}: {help}\n",
+                self.paint(BLUE, "="),
+                self.paint(CYAN, "help")
+            );
+        }
+
+        out
+    }
+
+    // every frame records where its function was called from, so each line
+    // belongs to the function of the next frame out, and the last to the script
+    fn traceback(&self, line: usize, trace: &[TraceFrame]) -> String {
+        if trace.is_empty() {
+            return String::new();
+        }
+
+        let lines = std::iter::once(line).chain(trace.iter().map(|frame| frame.line));
+        let names = trace
+            .iter()
+            .map(|frame| frame.name.as_str())
+            .chain(std::iter::once("<script>"));
+
+        let mut out = fmt!(
+            "{}\n",
+            self.paint(BOLD, "traceback (most recent call first):")
+        );
+
+        // deep recursion repeats the same frame, it's only shown once
+        let mut frames = lines.zip(names).peekable();
+        while let Some(frame) = frames.next() {
+            let mut repeated = 0;
+            while frames.next_if_eq(&frame).is_some() {
+                repeated += 1;
+            }
+
+            out += &fmt!("  line {}, in {}\n", frame.0, frame.1);
+            if repeated > 0 {
+                out += &fmt!("  [previous line repeated {repeated} times]\n");
+            }
+        }
+        out
+    }
+
+    // the line holding the start of the span, with the span clipped to it
+    fn snippet(&self, span: Span) -> Option<Snippet<'a>> {
+        let source = self.source;
+        if span.end > source.len() || !source.is_char_boundary(span.start) {
+            return None;
+        }
+
+        // an empty span at the end of the source (an unexpected end of file) is
+        // moved right after the last token, instead of the blank line after it
+        let mut start = span.start;
+        if span.start == span.end && span.start == source.len() {
+            start = source.trim_end().len();
+        }
+
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |i| start + i);
+        let text = source[line_start..line_end].trim_end_matches('\r');
+
+        let column = source[line_start..start].chars().count() + 1;
+        let end = span.end.clamp(start, line_end);
+        let width = source
+            .get(start..end)
+            .map_or(0, |s| s.chars().count())
+            .max(1);
+
+        Some(Snippet {
+            text,
+            line: source[..start].matches('\n').count() + 1,
+            column,
+            width,
+        })
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        match self.color {
+            true => fmt!("{color}{text}{RESET}"),
+            false => text.to_string(),
+        }
+    }
+}
+
+struct Snippet<'a> {
+    text: &'a str,
+    line: usize,
+    column: usize,
+    width: usize,
+}
+
+// notes for the errors whose fix isn't obvious from the message alone
+fn help(msg: &str) -> Option<&'static str> {
+    let help = match msg {
+        m if m.starts_with("Undefined variable") => {
+            "variables must be declared with 'let' before they are used"
+        }
+        "Can't read local variable in its own initializer" => {
+            "an outer variable with the same name is shadowed from the start of the declaration"
+        }
+        "Can't return from top-level code" => "'return' can only be used inside a function",
+        "Can't return a value from an initializer" => "'init' always returns the new instance",
+        "Can't use 'super' in a class with no superclass" => {
+            "inherit from a class with 'class Name < Superclass'"
+        }
+        _ => return None,
+    };
+    Some(help)
+}
@@ -2,53 +2,81 @@ use crate::{error::InterpErr, obj::LoxObject, token::Token};
 use format as fmt;
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
+// Values live in slots, in the order they were defined. Locals are reached by
+// the (depth, slot) pair computed by the resolver, while globals are still
+// looked up by name, since they can be referenced before being declared.
 #[derive(Clone)]
 pub struct Environment {
-    pub variables: HashMap<String, LoxObject>,
+    pub values: Vec<LoxObject>,
+    pub names: HashMap<String, usize>,
     pub enclosing: Option<Rc<RefCell<Environment>>>,
 }
 
 impl Environment {
     pub fn new(enclosing: Option<Rc<RefCell<Environment>>>) -> Self {
         Self {
-            variables: HashMap::new(),
+            values: Vec::new(),
+            names: HashMap::new(),
             enclosing,
         }
     }
 
+    // redefining a name (only allowed for globals) reuses its slot
     pub fn define(&mut self, key: &str, value: LoxObject) {
-        self.variables.insert(key.to_string(), value);
+        match self.names.get(key) {
+            Some(&slot) => self.values[slot] = value,
+            None => {
+                self.names.insert(key.to_string(), self.values.len());
+                self.values.push(value);
+            }
+        }
     }
 
     pub fn assign(&mut self, key: Token, value: LoxObject) -> Result<LoxObject, InterpErr> {
-        match self.variables.get(&key.lexeme) {
-            Some(_) => Ok(self.variables.insert(key.lexeme, value).unwrap()),
-            None => {
-                if let Some(e) = &self.enclosing {
-                    return RefCell::borrow_mut(e).assign(key, value);
-                }
-
-                Err(InterpErr::RuntimeError {
-                    line: key.line,
-                    msg: fmt!("Undefined variable '{}'", key.lexeme),
-                })
-            }
+        match self.names.get(&key.lexeme) {
+            Some(&slot) => Ok(std::mem::replace(&mut self.values[slot], value)),
+            None => Err(InterpErr::RuntimeError {
+                line: key.line,
+                span: key.span,
+                msg: fmt!("Undefined variable '{}'", key.lexeme),
+                trace: Vec::new(),
+            }),
         }
     }
 
     pub fn get(&self, key: &Token) -> Result<LoxObject, InterpErr> {
-        match self.variables.get(&key.lexeme) {
-            Some(v) => Ok(v.clone()),
-            None => {
-                if let Some(e) = &self.enclosing {
-                    return RefCell::borrow_mut(e).get(key);
-                };
-
-                Err(InterpErr::RuntimeError {
-                    line: key.line,
-                    msg: fmt!("Undefined variable '{}'", key.lexeme),
-                })
-            }
+        match self.names.get(&key.lexeme) {
+            Some(&slot) => Ok(self.values[slot].clone()),
+            None => Err(InterpErr::RuntimeError {
+                line: key.line,
+                span: key.span,
+                msg: fmt!("Undefined variable '{}'", key.lexeme),
+                trace: Vec::new(),
+            }),
         }
     }
+
+    pub fn get_at(&self, depth: usize, slot: usize) -> LoxObject {
+        if depth == 0 {
+            return self.values[slot].clone();
+        }
+
+        RefCell::borrow(self.ancestor()).get_at(depth - 1, slot)
+    }
+
+    pub fn assign_at(&mut self, depth: usize, slot: usize, value: LoxObject) {
+        if depth == 0 {
+            self.values[slot] = value;
+            return;
+        }
+
+        RefCell::borrow_mut(self.ancestor()).assign_at(depth - 1, slot, value)
+    }
+
+    // the resolver guarantees that every depth it hands out exists at runtime
+    fn ancestor(&self) -> &Rc<RefCell<Environment>> {
+        self.enclosing
+            .as_ref()
+            .expect("resolved depth is deeper than the environment chain")
+    }
 }
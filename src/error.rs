@@ -1,19 +1,155 @@
-use thiserror::Error;
+use std::fmt::{Debug, Display};
 
-use crate::obj::LoxObject;
+use crate::{diagnostic::Diagnostic, obj::LoxObject, token::Span, vm::Value};
 
-#[derive(Error, Debug)]
+// every error keeps the span of the source it refers to, so it can be pointed at.
+// Returning from a function isn't an error: see 'Flow' in the interpreter
+#[derive(Debug, Clone)]
 pub enum InterpErr {
-    #[error("SyntaxError [line {line}] Error: {msg} '{place}'")]
     SyntaxError {
         line: usize,
+        span: Span,
         msg: String,
         place: String,
     },
 
-    #[error("RuntimeError [line {line}] Error: {msg}")]
-    RuntimeError { line: usize, msg: String },
+    RuntimeError {
+        line: usize,
+        span: Span,
+        msg: String,
+        trace: Vec<TraceFrame>, //calls the error escaped from, innermost first
+    },
+
+    // a value thrown by 'throw' and not caught (yet)
+    Thrown {
+        line: usize,
+        span: Span,
+        value: ThrownValue,
+        trace: Vec<TraceFrame>,
+    },
+}
+
+// errors print the way they are reported, see 'Diagnostic'. The source isn't
+// known here, so the report has no snippet
+impl Display for InterpErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let diagnostic = Diagnostic::new("<source>", "").with_color(false);
+        write!(f, "{}", diagnostic.render(self))
+    }
+}
+
+impl std::error::Error for InterpErr {}
+
+// the thrown value as the backend that threw it represents it
+#[derive(Clone)]
+pub enum ThrownValue {
+    Object(LoxObject),
+    Value(Value),
+}
+
+impl Display for ThrownValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThrownValue::Object(o) => write!(f, "{o}"),
+            ThrownValue::Value(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+impl Debug for ThrownValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl InterpErr {
+    pub fn span(&self) -> Span {
+        match self {
+            InterpErr::SyntaxError { span, .. }
+            | InterpErr::RuntimeError { span, .. }
+            | InterpErr::Thrown { span, .. } => *span,
+        }
+    }
+
+    // gives a position to errors raised without one (by natives)
+    pub fn located(self, at_line: usize, at_span: Span) -> Self {
+        match self {
+            InterpErr::RuntimeError {
+                line: 0,
+                msg,
+                trace,
+                ..
+            } => InterpErr::RuntimeError {
+                line: at_line,
+                span: at_span,
+                msg,
+                trace,
+            },
+            e => e,
+        }
+    }
+
+    // the first call a runtime error escapes from records the whole call stack,
+    // the ones further out leave it untouched
+    pub fn traced(mut self, stack: &[TraceFrame]) -> Self {
+        if let InterpErr::RuntimeError { trace, .. } | InterpErr::Thrown { trace, .. } = &mut self {
+            if trace.is_empty() {
+                *trace = stack.iter().rev().cloned().collect();
+            }
+        }
+        self
+    }
+
+    // the value a 'catch' receives for a runtime error. Thrown values and syntax
+    // errors aren't turned into one
+    pub fn caught(self) -> Result<CaughtError, InterpErr> {
+        match self {
+            InterpErr::RuntimeError {
+                line,
+                span,
+                msg,
+                trace,
+            } => Ok(CaughtError {
+                line,
+                span,
+                msg,
+                trace,
+            }),
+            e => Err(e),
+        }
+    }
+}
+
+// a runtime error caught by a script. Scripts can read its 'message' and 'line',
+// and throwing it again raises the original error, traceback included
+#[derive(Debug)]
+pub struct CaughtError {
+    pub line: usize,
+    pub span: Span,
+    pub msg: String,
+    pub trace: Vec<TraceFrame>,
+}
+
+impl CaughtError {
+    pub fn rethrown(&self) -> InterpErr {
+        InterpErr::RuntimeError {
+            line: self.line,
+            span: self.span,
+            msg: self.msg.clone(),
+            trace: self.trace.clone(),
+        }
+    }
+}
+
+// a call that was in progress: the function called and the line it was called from
+#[derive(Debug, Clone)]
+pub struct TraceFrame {
+    pub name: String,
+    pub line: usize,
+}
 
-    #[error("ReturnError")]
-    Return { value: Option<LoxObject> },
+impl TraceFrame {
+    pub fn new(name: String, line: usize) -> Self {
+        Self { name, line }
+    }
 }
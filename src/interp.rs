@@ -1,17 +1,34 @@
 use crate::{
     ast::{
-        Assign, Binary, Call, Expr, ExprStmt, FnStmt, IfStmt, LetStmt, Literal, Logical,
-        ReturnStmt, Stmt, Unary, WhileStmt,
+        Assign, Binary, Call, ClassStmt, Conditional, Expr, ExprStmt, FnStmt, ForStmt, Get, IfStmt,
+        Index, Interpolation, LetStmt, ListExpr, Literal, Local, Logical, MapExpr, ReturnStmt, Set,
+        SetIndex, Stmt, SuperExpr, ThrowStmt, TryStmt, Unary, Update, WhileStmt,
     },
     env::Environment,
     error::InterpErr,
-    error::InterpErr as Ie,
-    loxstd::{Clock, Print},
-    obj::{LoxFunction, LoxObject},
-    token::TokenKind as Tk,
+    error::{InterpErr as Ie, ThrownValue, TraceFrame},
+    loxstd::{list_index, Clock, Eprint, NativeMethod, Print, Range},
+    map::{MapKey, OrderedMap},
+    number::{
+        add, divide, floor_divide, integer_op, modulo, multiply, power, shift_left, shift_right,
+        subtract, ArithmeticFn, IntegerFn, Number,
+    },
+    obj::{Callable, LoxClass, LoxFunction, LoxInstance, LoxObject},
+    token::{Span, Token, TokenKind as Tk},
+    vm::FRAMES_MAX,
 };
 use format as fmt;
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+// how a statement finished: normally, by a 'break' or 'continue' (with the
+// label it names, if any) that still has to reach its loop, or by a 'return'
+// that still has to reach its function. Errors are the only thing left to 'Err'
+pub enum Flow {
+    Normal,
+    Break(Option<String>),
+    Continue(Option<String>),
+    Return(LoxObject),
+}
 
 pub struct Interpreter {
     //represents the current environment being used by the interpreter
@@ -19,6 +36,9 @@ pub struct Interpreter {
 
     //represents the global environment
     pub globals: Rc<RefCell<Environment>>,
+
+    //calls in progress, attached to the runtime errors that escape them
+    call_stack: Vec<TraceFrame>,
 }
 
 impl Interpreter {
@@ -27,59 +47,237 @@ impl Interpreter {
 
         RefCell::borrow_mut(&mut globals).define("clock", LoxObject::Callable(Box::new(Clock {})));
         RefCell::borrow_mut(&mut globals).define("print", LoxObject::Callable(Box::new(Print {})));
+        globals
+            .borrow_mut()
+            .define("eprint", LoxObject::Callable(Box::new(Eprint {})));
+        globals
+            .borrow_mut()
+            .define("range", LoxObject::Callable(Box::new(Range {})));
         Self {
             env: Rc::clone(&globals),
             globals: Rc::clone(&globals),
+            call_stack: Vec::new(),
         }
     }
 
     pub fn interpret(&mut self, stmts: Vec<Stmt>) -> Result<(), InterpErr> {
         Ok(for stmt in stmts {
-            self.execute(&stmt)?
+            self.execute(&stmt)?;
         })
     }
 
-    fn execute(&mut self, s: &Stmt) -> Result<(), InterpErr> {
+    fn execute(&mut self, s: &Stmt) -> Result<Flow, InterpErr> {
         match s {
-            Stmt::ExprStmt(expr_stmt) => self.expr_stmt_exec(expr_stmt),
-            Stmt::LetStmt(let_stmt) => self.let_stmt_exec(let_stmt),
-            Stmt::Block(block) => self.block_stmt_exec(
-                block.iter().collect(),
-                Rc::new(RefCell::new(Environment::new(Some(self.env.clone())))),
-            ),
-            Stmt::IfStmt(if_stmt) => self.if_stmt_exec(if_stmt),
-            Stmt::WhileStmt(while_stmt) => self.while_stmt_exec(while_stmt),
-            Stmt::FnStmt(fn_stmt) => self.fn_stmt_exec(fn_stmt),
-            Stmt::ReturnStmt(return_stmt) => self.return_stmt_exec(return_stmt),
+            Stmt::ExprStmt(expr_stmt) => self.expr_stmt_exec(expr_stmt)?,
+            Stmt::LetStmt(let_stmt) => self.let_stmt_exec(let_stmt)?,
+            Stmt::Block(block) => {
+                return self.block_stmt_exec(
+                    block.iter().collect(),
+                    Rc::new(RefCell::new(Environment::new(Some(self.env.clone())))),
+                )
+            }
+            Stmt::IfStmt(if_stmt) => return self.if_stmt_exec(if_stmt),
+            Stmt::WhileStmt(while_stmt) => return self.while_stmt_exec(while_stmt),
+            Stmt::ForStmt(for_stmt) => return self.for_stmt_exec(for_stmt),
+            Stmt::FnStmt(fn_stmt) => self.fn_stmt_exec(fn_stmt)?,
+            Stmt::ReturnStmt(return_stmt) => return self.return_stmt_exec(return_stmt),
+            Stmt::BreakStmt(b) => {
+                return Ok(Flow::Break(b.label.as_ref().map(|l| l.lexeme.clone())))
+            }
+            Stmt::ContinueStmt(c) => {
+                return Ok(Flow::Continue(c.label.as_ref().map(|l| l.lexeme.clone())))
+            }
+            Stmt::TryStmt(try_stmt) => return self.try_stmt_exec(try_stmt),
+            Stmt::ThrowStmt(throw_stmt) => return self.throw_stmt_exec(throw_stmt),
+            Stmt::ClassStmt(class_stmt) => self.class_stmt_exec(class_stmt)?,
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn class_stmt_exec(&mut self, c: &ClassStmt) -> Result<(), InterpErr> {
+        let mut superclass = None;
+        if let Some(expr) = &c.superclass {
+            match self.evaluate(expr)? {
+                LoxObject::Class(class) => superclass = Some(class),
+                _ => {
+                    return Err(Ie::RuntimeError {
+                        line: c.ident.line,
+                        span: expr.span(),
+                        msg: "Superclass must be a class".to_string(),
+                        trace: Vec::new(),
+                    })
+                }
+            }
+        }
+
+        // methods of a subclass close over an extra environment that binds 'super'
+        let mut closure = Rc::clone(&self.env);
+        if let Some(class) = &superclass {
+            let mut env = Environment::new(Some(closure));
+            env.define("super", LoxObject::Class(Rc::clone(class)));
+            closure = Rc::new(RefCell::new(env));
         }
+
+        let mut methods = HashMap::new();
+        for method in &c.methods {
+            let is_initializer = method.ident.lexeme == "init";
+            let func = LoxFunction::new(method.clone(), Rc::clone(&closure), is_initializer);
+            methods.insert(method.ident.lexeme.clone(), func);
+        }
+
+        let class = LoxClass::new(c.ident.lexeme.clone(), superclass, methods);
+        RefCell::borrow_mut(&self.env).define(&c.ident.lexeme, LoxObject::Class(Rc::new(class)));
+        Ok(())
     }
 
-    fn return_stmt_exec(&mut self, r: &ReturnStmt) -> Result<(), InterpErr> {
-        let mut value = None;
+    fn return_stmt_exec(&mut self, r: &ReturnStmt) -> Result<Flow, InterpErr> {
+        let mut value = LoxObject::Null;
 
         if let Some(v) = &r.expr {
-            value = Some(self.evaluate(&v)?);
+            value = self.evaluate(&v)?;
+        }
+
+        Ok(Flow::Return(value))
+    }
+
+    // the finally block runs however the rest ended, and only replaces that
+    // ending if it ends with a 'break', 'continue', 'return' or error itself
+    fn try_stmt_exec(&mut self, t: &TryStmt) -> Result<Flow, InterpErr> {
+        let mut result = self.execute(&t.body);
+
+        if let Some((ident, body)) = &t.catch_clause {
+            result = match result {
+                Err(err) => self.catch_exec(err, ident, body),
+                result => result,
+            };
         }
 
-        Err(Ie::Return { value })
+        if let Some(body) = &t.finally_body {
+            match self.execute(body)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+
+        result
+    }
+
+    // runs the catch block with the value it receives for the error
+    fn catch_exec(
+        &mut self,
+        err: InterpErr,
+        ident: &Token,
+        body: &Stmt,
+    ) -> Result<Flow, InterpErr> {
+        let value = match err {
+            Ie::Thrown {
+                value: ThrownValue::Object(value),
+                ..
+            } => value,
+            // the stack the error was raised in, if no call has recorded it yet
+            err => LoxObject::Error(Rc::new(err.traced(&self.call_stack).caught()?)),
+        };
+
+        let mut env = Environment::new(Some(Rc::clone(&self.env)));
+        env.define(&ident.lexeme, value);
+        self.block_stmt_exec(vec![body], Rc::new(RefCell::new(env)))
+    }
+
+    // a caught runtime error is raised again as it was
+    fn throw_stmt_exec(&mut self, t: &ThrowStmt) -> Result<Flow, InterpErr> {
+        let value = self.evaluate(&t.value)?;
+        if let LoxObject::Error(err) = value {
+            return Err(err.rethrown());
+        }
+
+        Err(Ie::Thrown {
+            line: t.keyword.line,
+            span: t.keyword.span.to(t.value.span()),
+            value: ThrownValue::Object(value),
+            trace: Vec::new(),
+        })
     }
 
     fn fn_stmt_exec(&mut self, f: &FnStmt) -> Result<(), InterpErr> {
-        let func = LoxFunction::new(f.clone(), Rc::clone(&self.env));
+        let func = LoxFunction::new(f.clone(), Rc::clone(&self.env), false);
         RefCell::borrow_mut(&mut self.env)
             .define(&f.ident.lexeme, LoxObject::Callable(Box::new(func)));
         Ok(())
     }
 
-    fn while_stmt_exec(&mut self, w: &WhileStmt) -> Result<(), InterpErr> {
+    // the increment of a desugared 'for' runs after 'continue' too
+    fn while_stmt_exec(&mut self, w: &WhileStmt) -> Result<Flow, InterpErr> {
         while truthy(&self.evaluate(&w.condition)?) {
-            self.execute(&w.body)?
+            match self.execute(&w.body)? {
+                Flow::Break(label) if targets(&label, &w.label) => break,
+                Flow::Continue(label) if targets(&label, &w.label) => (),
+                Flow::Normal => (),
+                flow => return Ok(flow),
+            }
+
+            if let Some(increment) = &w.increment {
+                self.evaluate(increment)?;
+            }
         }
 
-        Ok(())
+        Ok(Flow::Normal)
+    }
+
+    // every iteration runs the body in a new environment holding only the loop
+    // variable, so closures created in the body capture that iteration's value
+    fn for_stmt_exec(&mut self, f: &ForStmt) -> Result<Flow, InterpErr> {
+        let iterable = match self.evaluate(&f.range)? {
+            LoxObject::Range(start, end) => LoxObject::Range(start, end),
+            LoxObject::List(list) => LoxObject::List(list),
+            // maps and strings are iterated over a snapshot of their keys and characters
+            LoxObject::Map(map) => {
+                let keys = RefCell::borrow(&map).keys().map(LoxObject::from).collect();
+                LoxObject::List(Rc::new(RefCell::new(keys)))
+            }
+            LoxObject::Str(s) => {
+                let chars = s.chars().map(|c| LoxObject::Str(c.to_string())).collect();
+                LoxObject::List(Rc::new(RefCell::new(chars)))
+            }
+            _ => {
+                return Err(Ie::RuntimeError {
+                    line: f.ident.line,
+                    span: f.range.span(),
+                    msg: "Can only iterate over lists, maps, strings and ranges".to_string(),
+                    trace: Vec::new(),
+                })
+            }
+        };
+
+        let mut i = 0;
+        loop {
+            // lists are read as they are iterated, so pushing to them extends the loop
+            let item = match &iterable {
+                LoxObject::List(list) => RefCell::borrow(list).get(i).cloned(),
+                LoxObject::Range(start, end) => {
+                    let n = start + i as i64;
+                    (n < *end).then_some(LoxObject::Number(Number::Int(n)))
+                }
+                _ => unreachable!("every iterable was turned into a list or a range"),
+            };
+
+            let Some(item) = item else {
+                return Ok(Flow::Normal);
+            };
+
+            let mut env = Environment::new(Some(Rc::clone(&self.env)));
+            env.define(&f.ident.lexeme, item);
+            match self.block_stmt_exec(vec![&f.body], Rc::new(RefCell::new(env)))? {
+                Flow::Break(label) if targets(&label, &f.label) => return Ok(Flow::Normal),
+                Flow::Continue(label) if targets(&label, &f.label) => (),
+                Flow::Normal => (),
+                flow => return Ok(flow),
+            }
+            i += 1;
+        }
     }
 
-    fn if_stmt_exec(&mut self, c: &IfStmt) -> Result<(), InterpErr> {
+    fn if_stmt_exec(&mut self, c: &IfStmt) -> Result<Flow, InterpErr> {
         let condition = truthy(&self.evaluate(&c.condition)?);
 
         if condition {
@@ -87,23 +285,42 @@ impl Interpreter {
         } else {
             match &c.else_branch {
                 Some(branch) => self.execute(&branch),
-                None => Ok(()),
+                None => Ok(Flow::Normal),
             }
         }
     }
 
     //sets the new env as the current one, executes
     //all statements and then sets the env as the previos one again
+    //a 'break', 'continue' or 'return' skips the rest of the statements
     pub fn block_stmt_exec(
         &mut self,
         stmts: Vec<&Stmt>,
         new_env: Rc<RefCell<Environment>>,
-    ) -> Result<(), InterpErr> {
+    ) -> Result<Flow, InterpErr> {
         let previous = Rc::clone(&self.env);
         self.env = new_env;
 
-        let result = stmts.into_iter().try_for_each(|stat| self.execute(stat));
+        let mut result = Ok(Flow::Normal);
+        for stat in stmts {
+            result = self.execute(stat);
+            if !matches!(result, Ok(Flow::Normal)) {
+                break;
+            }
+        }
+
+        self.env = previous;
+        result
+    }
 
+    // evaluates an expression as if it was inside of 'env'
+    pub fn evaluate_in(
+        &mut self,
+        e: &Expr,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<LoxObject, InterpErr> {
+        let previous = std::mem::replace(&mut self.env, env);
+        let result = self.evaluate(e);
         self.env = previous;
         result
     }
@@ -132,12 +349,233 @@ impl Interpreter {
             Expr::Assign(assign) => self.assign_eval(assign),
             Expr::Unary(unary) => self.unary_eval(unary),
             Expr::Binary(binary) => self.binary_eval(binary),
-            Expr::Grouping(expr) => self.evaluate(expr),
-            Expr::Var(v) => RefCell::borrow_mut(&self.env).get(v),
-            Expr::Lit(literal) => self.literal_eval(literal),
+            Expr::Grouping(grouping) => self.evaluate(&grouping.expr),
+            Expr::Var(v) => self.lookup(&v.ident, v.local),
+            Expr::Lit(lit) => self.literal_eval(&lit.value),
             Expr::Logical(logical) => self.logical_eval(logical),
             Expr::Call(call) => self.call_eval(call),
+            Expr::Get(get) => self.get_eval(get),
+            Expr::Set(set) => self.set_eval(set),
+            Expr::This(this) => self.lookup(&this.ident, this.local),
+            Expr::SuperExpr(super_expr) => self.super_eval(super_expr),
+            Expr::List(list) => self.list_eval(list),
+            Expr::Index(index) => self.index_eval(index),
+            Expr::SetIndex(set_index) => self.set_index_eval(set_index),
+            Expr::Update(update) => self.update_eval(update),
+            Expr::Conditional(conditional) => self.conditional_eval(conditional),
+            Expr::Interpolation(interpolation) => self.interpolation_eval(interpolation),
+            Expr::Map(map) => self.map_eval(map),
+            Expr::Lambda(lambda) => {
+                let func = LoxFunction::new(lambda.function.clone(), Rc::clone(&self.env), false);
+                Ok(LoxObject::Callable(Box::new(func)))
+            }
+        }
+    }
+
+    // locals are read straight from their resolved slot, anything else is a global
+    fn lookup(&self, ident: &Token, local: Option<Local>) -> Result<LoxObject, InterpErr> {
+        match local {
+            Some(Local { depth, slot }) => Ok(RefCell::borrow(&self.env).get_at(depth, slot)),
+            None => RefCell::borrow(&self.globals).get(ident),
+        }
+    }
+
+    // looks the method up starting at the superclass, but binds it to the current 'this'.
+    // 'this' always lives in the environment right below the one binding 'super'
+    fn super_eval(&mut self, s: &SuperExpr) -> Result<LoxObject, InterpErr> {
+        let Some(Local { depth, slot }) = s.local else {
+            return rt_error(
+                s.keyword.line,
+                s.span,
+                "Can't use 'super' outside of a method",
+            );
+        };
+
+        let superclass = match RefCell::borrow(&self.env).get_at(depth, slot) {
+            LoxObject::Class(class) => class,
+            _ => return rt_error(s.keyword.line, s.span, "'super' must refer to a class"),
+        };
+
+        let instance = match RefCell::borrow(&self.env).get_at(depth - 1, 0) {
+            LoxObject::Instance(instance) => instance,
+            _ => {
+                return rt_error(
+                    s.keyword.line,
+                    s.span,
+                    "Can't use 'super' outside of a method",
+                )
+            }
+        };
+
+        match superclass.find_method(&s.method.lexeme) {
+            Some(method) => Ok(LoxObject::Callable(Box::new(method.bind(instance)))),
+            None => rt_error(
+                s.method.line,
+                s.method.span,
+                &fmt!("Undefined property '{}'", s.method.lexeme),
+            ),
+        }
+    }
+
+    fn get_eval(&mut self, g: &Get) -> Result<LoxObject, InterpErr> {
+        let object = self.evaluate(&g.object)?;
+        self.property(object, g)
+    }
+
+    fn property(&self, object: LoxObject, g: &Get) -> Result<LoxObject, InterpErr> {
+        match object {
+            LoxObject::Instance(instance) => return LoxInstance::get(&instance, &g.ident),
+            object @ (LoxObject::List(_) | LoxObject::Map(_)) => {
+                return NativeMethod::bind(object, &g.ident)
+            }
+            LoxObject::Error(err) => match g.ident.lexeme.as_str() {
+                "message" => return Ok(LoxObject::Str(err.msg.clone())),
+                "line" => return Ok(LoxObject::Number(Number::Int(err.line as i64))),
+                name => {
+                    return rt_error(
+                        g.ident.line,
+                        g.ident.span,
+                        &fmt!("Undefined property '{name}'"),
+                    )
+                }
+            },
+            _ => (),
+        }
+
+        rt_error(
+            g.ident.line,
+            g.object.span(),
+            "Only instances have properties",
+        )
+    }
+
+    fn set_eval(&mut self, s: &Set) -> Result<LoxObject, InterpErr> {
+        let object = self.evaluate(&s.object)?;
+
+        if let LoxObject::Instance(_) = object {
+            let value = self.evaluate(&s.value)?;
+            return self.set_property(object, &s.ident, &s.object, value);
+        }
+
+        rt_error(s.ident.line, s.object.span(), "Only instances have fields")
+    }
+
+    fn set_property(
+        &self,
+        object: LoxObject,
+        ident: &Token,
+        object_expr: &Expr,
+        value: LoxObject,
+    ) -> Result<LoxObject, InterpErr> {
+        if let LoxObject::Instance(instance) = object {
+            RefCell::borrow_mut(&instance).set(ident, value.clone());
+            return Ok(value);
+        }
+
+        rt_error(ident.line, object_expr.span(), "Only instances have fields")
+    }
+
+    fn list_eval(&mut self, l: &ListExpr) -> Result<LoxObject, InterpErr> {
+        let mut elements = Vec::new();
+        for element in &l.elements {
+            elements.push(self.evaluate(element)?);
+        }
+
+        Ok(LoxObject::List(Rc::new(RefCell::new(elements))))
+    }
+
+    // every entry is evaluated before the map is built, like in the VM, so key
+    // errors point at the whole literal
+    fn map_eval(&mut self, m: &MapExpr) -> Result<LoxObject, InterpErr> {
+        let mut entries = Vec::new();
+        for (key_expr, value_expr) in &m.entries {
+            entries.push((self.evaluate(key_expr)?, self.evaluate(value_expr)?));
+        }
+
+        let mut map = OrderedMap::new();
+        for (key, value) in entries {
+            match key.to_key() {
+                Ok(key) => map.insert(key, value),
+                Err(msg) => return rt_error(m.brace.line, m.span, msg),
+            };
         }
+
+        Ok(LoxObject::Map(Rc::new(RefCell::new(map))))
+    }
+
+    fn index_eval(&mut self, i: &Index) -> Result<LoxObject, InterpErr> {
+        let object = self.evaluate(&i.object)?;
+        let index = self.evaluate(&i.index)?;
+        self.element(object, index, i)
+    }
+
+    fn element(
+        &self,
+        object: LoxObject,
+        index: LoxObject,
+        i: &Index,
+    ) -> Result<LoxObject, InterpErr> {
+        match object {
+            LoxObject::List(list) => {
+                let list = RefCell::borrow(&list);
+                let slot = list_slot(i.bracket.line, &i.index, &index, list.len())?;
+                Ok(list[slot].clone())
+            }
+            LoxObject::Map(map) => {
+                let key = map_key(i.bracket.line, &i.index, &index)?;
+                match RefCell::borrow(&map).get(&key) {
+                    Some(value) => Ok(value.clone()),
+                    None => rt_error(
+                        i.bracket.line,
+                        i.index.span(),
+                        &fmt!("Undefined key '{index}'"),
+                    ),
+                }
+            }
+            _ => rt_error(
+                i.bracket.line,
+                i.index.span(),
+                "Can only index lists and maps",
+            ),
+        }
+    }
+
+    fn set_index_eval(&mut self, s: &SetIndex) -> Result<LoxObject, InterpErr> {
+        let object = self.evaluate(&s.object)?;
+        let index = self.evaluate(&s.index)?;
+        let value = self.evaluate(&s.value)?;
+        self.set_element(object, index, value, &s.bracket, &s.index)
+    }
+
+    // index errors point at the index expression, like in the VM
+    fn set_element(
+        &self,
+        object: LoxObject,
+        index: LoxObject,
+        value: LoxObject,
+        bracket: &Token,
+        index_expr: &Expr,
+    ) -> Result<LoxObject, InterpErr> {
+        match object {
+            LoxObject::List(list) => {
+                let mut list = RefCell::borrow_mut(&list);
+                let slot = list_slot(bracket.line, index_expr, &index, list.len())?;
+                list[slot] = value.clone();
+            }
+            LoxObject::Map(map) => {
+                let key = map_key(bracket.line, index_expr, &index)?;
+                RefCell::borrow_mut(&map).insert(key, value.clone());
+            }
+            _ => {
+                return rt_error(
+                    bracket.line,
+                    index_expr.span(),
+                    "Can only index lists and maps",
+                )
+            }
+        }
+
+        Ok(value)
     }
 
     fn literal_eval(&self, l: &Literal) -> Result<LoxObject, InterpErr> {
@@ -157,23 +595,33 @@ impl Interpreter {
         for arg in &c.args {
             args.push(self.evaluate(arg)?);
         }
+        let mut named = Vec::new();
+        for (ident, arg) in &c.named {
+            named.push((ident.lexeme.clone(), self.evaluate(arg)?));
+        }
 
-        if let LoxObject::Callable(callable) = callee {
-            if args.len() != callable.arity() {
-                return rt_error(
-                    c.paren.line,
-                    &fmt!(
-                        "Expected {} arguments, but {} where provided",
-                        callable.arity(),
-                        args.len()
-                    ),
-                );
-            }
+        let callable: &dyn Callable = match &callee {
+            LoxObject::Callable(callable) => callable.as_ref(),
+            LoxObject::Class(class) => class,
+            _ => return rt_error(c.paren.line, c.span, "Can only call functions and classes"),
+        };
+
+        let args = match callable.signature().bind(args, named) {
+            Ok(args) => args,
+            Err(msg) => return rt_error(c.paren.line, c.span, &msg),
+        };
 
-            return callable.call(self, args);
+        if self.call_stack.len() + 1 >= FRAMES_MAX {
+            return rt_error(c.paren.line, c.span, "Stack overflow");
         }
 
-        return rt_error(c.paren.line, "Can only call functions and classes");
+        self.call_stack
+            .push(TraceFrame::new(callable.to_string(), c.paren.line));
+        let result = callable
+            .call_bound(self, args)
+            .map_err(|e| e.located(c.paren.line, c.span).traced(&self.call_stack));
+        self.call_stack.pop();
+        result
     }
 
     fn logical_eval(&mut self, l: &Logical) -> Result<LoxObject, InterpErr> {
@@ -183,6 +631,10 @@ impl Interpreter {
             if truthy(&left) {
                 return Ok(left);
             }
+        } else if let Tk::QuestionQuestion = l.operator.kind {
+            if !matches!(left, LoxObject::Null) {
+                return Ok(left);
+            }
         } else {
             if !truthy(&left) {
                 return Ok(left);
@@ -192,75 +644,86 @@ impl Interpreter {
         self.evaluate(&l.right)
     }
 
+    fn conditional_eval(&mut self, c: &Conditional) -> Result<LoxObject, InterpErr> {
+        if truthy(&self.evaluate(&c.condition)?) {
+            self.evaluate(&c.then_branch)
+        } else {
+            self.evaluate(&c.else_branch)
+        }
+    }
+
+    // every part is converted the way 'print' shows it
+    fn interpolation_eval(&mut self, i: &Interpolation) -> Result<LoxObject, InterpErr> {
+        let mut string = String::new();
+        for part in &i.parts {
+            string += &self.evaluate(part)?.to_string();
+        }
+
+        Ok(LoxObject::Str(string))
+    }
+
     fn assign_eval(&mut self, a: &Assign) -> Result<LoxObject, InterpErr> {
         let value = self.evaluate(&a.value)?;
-        RefCell::borrow_mut(&self.env).assign(a.ident.clone(), value.clone())?;
+        self.assign_variable(&a.ident, a.local, value.clone())?;
         Ok(value)
     }
 
+    fn assign_variable(
+        &mut self,
+        ident: &Token,
+        local: Option<Local>,
+        value: LoxObject,
+    ) -> Result<(), InterpErr> {
+        match local {
+            Some(Local { depth, slot }) => {
+                RefCell::borrow_mut(&self.env).assign_at(depth, slot, value)
+            }
+            None => {
+                RefCell::borrow_mut(&self.globals).assign(ident.clone(), value)?;
+            }
+        }
+        Ok(())
+    }
+
     fn binary_eval(&mut self, b: &Binary) -> Result<LoxObject, InterpErr> {
         let left = self.evaluate(&b.left)?;
         let right = self.evaluate(&b.right)?;
+        binary_op(&b.operator, left, right, b.span)
+    }
 
-        match b.operator.kind {
-            Tk::Minus => {
-                if let (LoxObject::Number(l), LoxObject::Number(r)) = (left, right) {
-                    return Ok(LoxObject::Number(l - r));
-                }
-
-                rt_error(b.operator.line, "Operands must be number")
-            }
-            Tk::Slash => {
-                if let (LoxObject::Number(l), LoxObject::Number(r)) = (left, right) {
-                    return Ok(LoxObject::Number(l / r));
-                }
-
-                rt_error(b.operator.line, "Operands must be number")
-            }
-            Tk::Star => {
-                if let (LoxObject::Number(l), LoxObject::Number(r)) = (left, right) {
-                    return Ok(LoxObject::Number(l * r));
-                }
-
-                rt_error(b.operator.line, "Operands must be number")
+    // evaluates the target of the update once, storing 'old <op> value' back in it
+    fn update_eval(&mut self, u: &Update) -> Result<LoxObject, InterpErr> {
+        let (old, new) = match &*u.target {
+            Expr::Var(var) => {
+                let old = self.lookup(&var.ident, var.local)?;
+                let new = self.updated(u, old.clone())?;
+                self.assign_variable(&var.ident, var.local, new.clone())?;
+                (old, new)
             }
-            Tk::Plus => match (left, right) {
-                (LoxObject::Number(l), LoxObject::Number(r)) => Ok(LoxObject::Number(l + r)),
-                (LoxObject::Str(l), LoxObject::Str(r)) => Ok(LoxObject::Str(l + &r)),
-                _ => rt_error(b.operator.line, "Operand must be 'string' or 'number'"),
-            },
-            Tk::Greater => {
-                if let (LoxObject::Number(l), LoxObject::Number(r)) = (left, right) {
-                    return Ok(LoxObject::Bool(l > r));
-                }
-
-                rt_error(b.operator.line, "Operands must be number")
+            Expr::Get(get) => {
+                let object = self.evaluate(&get.object)?;
+                let old = self.property(object.clone(), get)?;
+                let new = self.updated(u, old.clone())?;
+                self.set_property(object, &get.ident, &get.object, new.clone())?;
+                (old, new)
             }
-            Tk::GreaterEqual => {
-                if let (LoxObject::Number(l), LoxObject::Number(r)) = (left, right) {
-                    return Ok(LoxObject::Bool(l >= r));
-                }
-
-                rt_error(b.operator.line, "Operands must be number")
+            Expr::Index(index) => {
+                let object = self.evaluate(&index.object)?;
+                let key = self.evaluate(&index.index)?;
+                let old = self.element(object.clone(), key.clone(), index)?;
+                let new = self.updated(u, old.clone())?;
+                self.set_element(object, key, new.clone(), &index.bracket, &index.index)?;
+                (old, new)
             }
-            Tk::Less => {
-                if let (LoxObject::Number(l), LoxObject::Number(r)) = (left, right) {
-                    return Ok(LoxObject::Bool(l < r));
-                }
+            _ => unreachable!("the parser only updates variables, properties and elements"),
+        };
 
-                rt_error(b.operator.line, "Operands must be number")
-            }
-            Tk::LessEqual => {
-                if let (LoxObject::Number(l), LoxObject::Number(r)) = (left, right) {
-                    return Ok(LoxObject::Bool(l <= r));
-                }
+        Ok(if u.postfix { old } else { new })
+    }
 
-                rt_error(b.operator.line, "Operands must be number")
-            }
-            Tk::BangEqual => Ok(LoxObject::Bool(left != right)),
-            Tk::EqualEqual => Ok(LoxObject::Bool(left == right)),
-            _ => rt_error(b.operator.line, "Invalid operator"),
-        }
+    fn updated(&mut self, u: &Update, old: LoxObject) -> Result<LoxObject, InterpErr> {
+        let value = self.evaluate(&u.value)?;
+        binary_op(&u.operator, old, value, u.span)
     }
 
     fn unary_eval(&mut self, u: &Unary) -> Result<LoxObject, InterpErr> {
@@ -268,25 +731,149 @@ impl Interpreter {
 
         match u.operator.kind {
             Tk::Bang => Ok(LoxObject::Bool(!truthy(&right))),
-            Tk::Minus => {
-                if let LoxObject::Number(n) = right {
-                    return Ok(LoxObject::Number(-n));
+            Tk::Minus | Tk::Tilde => {
+                let LoxObject::Number(n) = right else {
+                    return rt_error(u.operator.line, u.span, "Operand must be a number");
+                };
+
+                let n = match u.operator.kind {
+                    Tk::Minus => n.negate(),
+                    _ => n.bit_not(),
+                };
+                match n {
+                    Ok(n) => Ok(LoxObject::Number(n)),
+                    Err(msg) => rt_error(u.operator.line, u.span, msg),
                 }
+            }
+            _ => rt_error(u.operator.line, u.span, "Invalid operator"),
+        }
+    }
+}
 
-                rt_error(u.operator.line, "Operand must be a number")
+// shared by binary expressions and updates
+fn binary_op(
+    operator: &Token,
+    left: LoxObject,
+    right: LoxObject,
+    span: Span,
+) -> Result<LoxObject, InterpErr> {
+    match operator.kind {
+        Tk::Minus | Tk::Slash | Tk::Star | Tk::Percent | Tk::SlashSlash | Tk::StarStar => {
+            let (LoxObject::Number(l), LoxObject::Number(r)) = (left, right) else {
+                return rt_error(operator.line, span, "Operands must be number");
+            };
+
+            let op: ArithmeticFn = match operator.kind {
+                Tk::Minus => subtract,
+                Tk::Slash => divide,
+                Tk::Star => multiply,
+                Tk::Percent => modulo,
+                Tk::SlashSlash => floor_divide,
+                _ => power,
+            };
+            match op(l, r) {
+                Ok(n) => Ok(LoxObject::Number(n)),
+                Err(msg) => rt_error(operator.line, span, msg),
+            }
+        }
+        Tk::Ampersand | Tk::Pipe | Tk::Caret | Tk::LessLess | Tk::GreaterGreater => {
+            let (LoxObject::Number(l), LoxObject::Number(r)) = (left, right) else {
+                return rt_error(operator.line, span, "Operands must be number");
+            };
+
+            let op: IntegerFn = match operator.kind {
+                Tk::Ampersand => |l, r| Some(l & r),
+                Tk::Pipe => |l, r| Some(l | r),
+                Tk::Caret => |l, r| Some(l ^ r),
+                Tk::LessLess => shift_left,
+                _ => shift_right,
+            };
+            match integer_op(l, r, op) {
+                Ok(n) => Ok(LoxObject::Number(n)),
+                Err(msg) => rt_error(operator.line, span, msg),
             }
-            _ => rt_error(u.operator.line, "Ivalid operator"),
         }
+        Tk::Plus => match (left, right) {
+            (LoxObject::Number(l), LoxObject::Number(r)) => match add(l, r) {
+                Ok(n) => Ok(LoxObject::Number(n)),
+                Err(msg) => rt_error(operator.line, span, msg),
+            },
+            (LoxObject::Str(l), LoxObject::Str(r)) => Ok(LoxObject::Str(l + &r)),
+            _ => rt_error(operator.line, span, "Operand must be 'string' or 'number'"),
+        },
+        Tk::Greater => {
+            if let (LoxObject::Number(l), LoxObject::Number(r)) = (left, right) {
+                return Ok(LoxObject::Bool(l > r));
+            }
+
+            rt_error(operator.line, span, "Operands must be number")
+        }
+        Tk::GreaterEqual => {
+            if let (LoxObject::Number(l), LoxObject::Number(r)) = (left, right) {
+                return Ok(LoxObject::Bool(l >= r));
+            }
+
+            rt_error(operator.line, span, "Operands must be number")
+        }
+        Tk::Less => {
+            if let (LoxObject::Number(l), LoxObject::Number(r)) = (left, right) {
+                return Ok(LoxObject::Bool(l < r));
+            }
+
+            rt_error(operator.line, span, "Operands must be number")
+        }
+        Tk::LessEqual => {
+            if let (LoxObject::Number(l), LoxObject::Number(r)) = (left, right) {
+                return Ok(LoxObject::Bool(l <= r));
+            }
+
+            rt_error(operator.line, span, "Operands must be number")
+        }
+        Tk::BangEqual => Ok(LoxObject::Bool(left != right)),
+        Tk::EqualEqual => Ok(LoxObject::Bool(left == right)),
+        _ => rt_error(operator.line, span, "Invalid operator"),
     }
 }
 
-fn rt_error(line: usize, msg: &str) -> Result<LoxObject, InterpErr> {
+fn rt_error(line: usize, span: Span, msg: &str) -> Result<LoxObject, InterpErr> {
     Err(Ie::RuntimeError {
         line,
+        span,
         msg: msg.to_string(),
+        trace: Vec::new(),
     })
 }
 
+// a bad index or key is reported at the index expression
+fn list_slot(line: usize, expr: &Expr, index: &LoxObject, len: usize) -> Result<usize, InterpErr> {
+    let slot = match index {
+        LoxObject::Number(Number::Int(n)) => list_index(*n, len),
+        _ => Err("List index must be an integer"),
+    };
+    slot.map_err(|msg| index_error(line, expr, msg))
+}
+
+fn map_key(line: usize, expr: &Expr, key: &LoxObject) -> Result<MapKey, InterpErr> {
+    key.to_key().map_err(|msg| index_error(line, expr, msg))
+}
+
+fn index_error(line: usize, expr: &Expr, msg: &str) -> InterpErr {
+    Ie::RuntimeError {
+        line,
+        span: expr.span(),
+        msg: msg.to_string(),
+        trace: Vec::new(),
+    }
+}
+
+// an unlabelled 'break' or 'continue' targets the innermost loop
+fn targets(target: &Option<String>, label: &Option<Token>) -> bool {
+    match target {
+        Some(target) => label.as_ref().is_some_and(|label| label.lexeme == *target),
+        None => true,
+    }
+}
+
 fn truthy(v: &LoxObject) -> bool {
     match v {
         LoxObject::Bool(b) => *b,
@@ -1,8 +1,11 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::Range};
+
+use unicode_ident::{is_xid_continue, is_xid_start};
 
 use crate::{
     error::InterpErr,
-    token::{Token, TokenKind},
+    number::Number,
+    token::{Span, Token, TokenKind},
 };
 
 // MENTAL MODEL:
@@ -11,13 +14,19 @@ use crate::{
 // single or double char: check if the next char is a match to the first char.
 // literals: go to the end of the literal and parses it into the object representation
 // unused: skip them
+// positions: 'current' and the spans are byte offsets into the source, so the
+// lexer walks it once, one char at a time. Columns count chars instead
 
 pub struct Lexer {
     source: String,
     tokens: Vec<Token>,
     current: usize,
     line: usize,
+    column: usize, //column of the next char
     start: usize,
+    start_line: usize,          //line where the current token begins
+    start_column: usize,        //column where the current token begins
+    interpolations: Vec<usize>, //open braces in each embedded expression being lexed
     keywords: HashMap<String, TokenKind>,
 }
 
@@ -26,17 +35,24 @@ impl Lexer {
         let mut keywords = HashMap::new();
         keywords.insert("and".into(), TokenKind::And);
         keywords.insert("or".into(), TokenKind::Or);
+        keywords.insert("break".into(), TokenKind::Break);
+        keywords.insert("catch".into(), TokenKind::Catch);
         keywords.insert("class".into(), TokenKind::Class);
+        keywords.insert("continue".into(), TokenKind::Continue);
         keywords.insert("else".into(), TokenKind::Else);
         keywords.insert("false".into(), TokenKind::False);
+        keywords.insert("finally".into(), TokenKind::Finally);
         keywords.insert("fn".into(), TokenKind::Fn);
         keywords.insert("for".into(), TokenKind::For);
         keywords.insert("if".into(), TokenKind::If);
+        keywords.insert("in".into(), TokenKind::In);
         keywords.insert("null".into(), TokenKind::Null);
         keywords.insert("return".into(), TokenKind::Return);
         keywords.insert("super".into(), TokenKind::Super);
         keywords.insert("this".into(), TokenKind::This);
+        keywords.insert("throw".into(), TokenKind::Throw);
         keywords.insert("true".into(), TokenKind::True);
+        keywords.insert("try".into(), TokenKind::Try);
         keywords.insert("let".into(), TokenKind::Let);
         keywords.insert("while".into(), TokenKind::While);
 
@@ -45,21 +61,47 @@ impl Lexer {
             tokens: Vec::new(),
             current: 0,
             line: 1,
+            column: 1,
             start: 0,
+            start_line: 1,
+            start_column: 1,
+            interpolations: Vec::new(),
             keywords,
         }
     }
 
-    pub fn tokenized(&mut self) -> Result<Vec<Token>, InterpErr> {
+    // an invalid character doesn't stop the lexer, so every one of them is reported
+    pub fn tokenized(&mut self) -> Result<Vec<Token>, Vec<InterpErr>> {
+        match self.scan() {
+            (tokens, errors) if errors.is_empty() => Ok(tokens),
+            (_, errors) => Err(errors),
+        }
+    }
+
+    // lexing goes on after an error, leaving out what didn't lex, so the parser
+    // can still report the errors in the rest of the source
+    pub fn scan(&mut self) -> (Vec<Token>, Vec<InterpErr>) {
+        let mut errors = Vec::new();
+
         while !self.finished() {
             self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.column;
 
-            self.process_next()?
+            if let Err(e) = self.process_next() {
+                errors.push(e);
+            }
         }
 
-        self.tokens
-            .push(Token::new(TokenKind::Eof, "".to_string(), self.line));
-        Ok(self.tokens.clone())
+        let span = Span::new(self.current, self.current);
+        self.tokens.push(Token::new(
+            TokenKind::Eof,
+            "".to_string(),
+            self.line,
+            self.column,
+            span,
+        ));
+        (self.tokens.clone(), errors)
     }
 
     fn process_next(&mut self) -> Result<(), InterpErr> {
@@ -75,39 +117,125 @@ impl Lexer {
                 self.add_token(TokenKind::RightParen);
                 Ok(())
             }
-            '{' => {
-                self.add_token(TokenKind::LeftBrace);
+            '[' => {
+                self.add_token(TokenKind::LeftBracket);
                 Ok(())
             }
-            '}' => {
-                self.add_token(TokenKind::RightBrace);
+            ']' => {
+                self.add_token(TokenKind::RightBracket);
                 Ok(())
             }
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                self.add_token(TokenKind::LeftBrace);
+                Ok(())
+            }
+            '}' => match self.interpolations.last_mut() {
+                // closes the embedded expression, the string goes on
+                Some(0) => {
+                    self.interpolations.pop();
+                    self.string_body(false, false, true)
+                }
+                depth => {
+                    if let Some(depth) = depth {
+                        *depth -= 1;
+                    }
+                    self.add_token(TokenKind::RightBrace);
+                    Ok(())
+                }
+            },
             ',' => {
                 self.add_token(TokenKind::Comma);
                 Ok(())
             }
+            ':' => {
+                self.add_token(TokenKind::Colon);
+                Ok(())
+            }
             '.' => {
-                self.add_token(TokenKind::Dot);
+                if self.peek() == '.' && self.peek_next() == '.' {
+                    self.next_char();
+                    self.next_char();
+                    self.add_token(TokenKind::Ellipsis);
+                } else {
+                    self.add_token(TokenKind::Dot);
+                }
                 Ok(())
             }
             ';' => {
                 self.add_token(TokenKind::Semicolon);
                 Ok(())
             }
+            '&' => {
+                self.add_token(TokenKind::Ampersand);
+                Ok(())
+            }
+            '|' => {
+                self.add_token(TokenKind::Pipe);
+                Ok(())
+            }
+            '^' => {
+                self.add_token(TokenKind::Caret);
+                Ok(())
+            }
+            '~' => {
+                self.add_token(TokenKind::Tilde);
+                Ok(())
+            }
+            //single or double char tokens
             '+' => {
-                self.add_token(TokenKind::Plus);
+                let kind = if self.complement('=') {
+                    TokenKind::PlusEqual
+                } else if self.complement('+') {
+                    TokenKind::PlusPlus
+                } else {
+                    TokenKind::Plus
+                };
+                self.add_token(kind);
                 Ok(())
             }
             '-' => {
-                self.add_token(TokenKind::Minus);
+                let kind = if self.complement('=') {
+                    TokenKind::MinusEqual
+                } else if self.complement('-') {
+                    TokenKind::MinusMinus
+                } else {
+                    TokenKind::Minus
+                };
+                self.add_token(kind);
+                Ok(())
+            }
+            '%' => {
+                let kind = if self.complement('=') {
+                    TokenKind::PercentEqual
+                } else {
+                    TokenKind::Percent
+                };
+                self.add_token(kind);
                 Ok(())
             }
             '*' => {
-                self.add_token(TokenKind::Star);
+                let kind = if self.complement('*') {
+                    TokenKind::StarStar
+                } else if self.complement('=') {
+                    TokenKind::StarEqual
+                } else {
+                    TokenKind::Star
+                };
+                self.add_token(kind);
+                Ok(())
+            }
+            '?' => {
+                let kind = if self.complement('?') {
+                    TokenKind::QuestionQuestion
+                } else {
+                    TokenKind::Question
+                };
+                self.add_token(kind);
                 Ok(())
             }
-            //single or double char tokens
             '!' => {
                 let kind = if self.complement('=') {
                     TokenKind::BangEqual
@@ -120,6 +248,8 @@ impl Lexer {
             '=' => {
                 let kind = if self.complement('=') {
                     TokenKind::EqualEqual
+                } else if self.complement('>') {
+                    TokenKind::Arrow
                 } else {
                     TokenKind::Equal
                 };
@@ -129,6 +259,8 @@ impl Lexer {
             '>' => {
                 let kind = if self.complement('=') {
                     TokenKind::GreaterEqual
+                } else if self.complement('>') {
+                    TokenKind::GreaterGreater
                 } else {
                     TokenKind::Greater
                 };
@@ -138,6 +270,8 @@ impl Lexer {
             '<' => {
                 let kind = if self.complement('=') {
                     TokenKind::LessEqual
+                } else if self.complement('<') {
+                    TokenKind::LessLess
                 } else {
                     TokenKind::Less
                 };
@@ -145,7 +279,13 @@ impl Lexer {
                 Ok(())
             }
             '/' => {
-                if self.complement('/') {
+                if self.operand_before() && self.complement('/') {
+                    self.add_token(TokenKind::SlashSlash);
+                    Ok(())
+                } else if self.complement('=') {
+                    self.add_token(TokenKind::SlashEqual);
+                    Ok(())
+                } else if self.complement('/') {
                     //if it is a comment, skip the line
                     while self.peek() != '\n' && !self.finished() {
                         self.next_char();
@@ -157,25 +297,53 @@ impl Lexer {
                 }
             }
             //literals
-            '"' => self.string(),
-            c if c.is_numeric() => self.number(),
-            c if c.is_alphabetic() || c == '_' => self.identifier(),
-            //meaningless chars
-            ' ' | '\r' | '\t' => Ok(()),
-            '\n' => {
-                self.line += 1;
-                Ok(())
+            '"' => self.string(false),
+            'r' if self.peek() == '"' => {
+                //consumes the '"' token
+                self.next_char();
+                self.string(true)
             }
+            c if c.is_ascii_digit() => self.number(),
+            c if is_xid_start(c) || c == '_' => self.identifier(),
+            //meaningless chars
+            ' ' | '\r' | '\t' | '\n' => Ok(()),
             c => Err(InterpErr::SyntaxError {
                 line: self.line,
+                span: self.span(),
                 msg: "Unexpected character".to_string(),
                 place: format!("{c}"),
             }),
         }
     }
 
+    // '//' is floor division when it follows an operand on the same line, and
+    // starts a comment anywhere else. A '}' doesn't count: it mostly closes a
+    // block, and comments follow those
+    fn operand_before(&self) -> bool {
+        match self.tokens.last() {
+            Some(token) if token.line == self.line => matches!(
+                token.kind,
+                TokenKind::Identifier
+                    | TokenKind::Number(_)
+                    | TokenKind::String(_)
+                    | TokenKind::InterpolatedEnd(_)
+                    | TokenKind::RightParen
+                    | TokenKind::RightBracket
+                    | TokenKind::PlusPlus
+                    | TokenKind::MinusMinus
+                    | TokenKind::True
+                    | TokenKind::False
+                    | TokenKind::Null
+                    | TokenKind::This
+            ),
+            _ => false,
+        }
+    }
+
+    // identifiers follow UAX #31: a letter or '_', then letters, digits, '_'
+    // and combining marks, in any script
     fn identifier(&mut self) -> Result<(), InterpErr> {
-        while self.peek().is_alphanumeric() {
+        while is_xid_continue(self.peek()) {
             self.next_char();
         }
 
@@ -193,50 +361,242 @@ impl Lexer {
         }
     }
 
+    // '123', '1.5', '1e-9' and '0xFF', '0o17', '0b1010', with '_' separating
+    // digits. Only a fraction or an exponent makes a float. Letters right after
+    // a number are part of it, so '12ab' or '0b12' are reported as a whole
     fn number(&mut self) -> Result<(), InterpErr> {
-        while self.peek().is_numeric() {
-            self.next_char();
-        }
+        let radix = match (&self.source[self.start..self.current], self.peek()) {
+            ("0", 'x') => 16,
+            ("0", 'o') => 8,
+            ("0", 'b') => 2,
+            _ => 10,
+        };
 
-        if self.peek() == '.' && self.peek_next().is_numeric() {
+        let mut groups = Vec::new(); //digits of each part of the number
+        if radix != 10 {
             self.next_char();
+            groups.push(self.digits(radix));
+        } else {
+            groups.push(self.start..self.digits(10).end);
+
+            if self.peek() == '.' && self.peek_next().is_ascii_digit() {
+                self.next_char();
+                groups.push(self.digits(10));
+            }
+            if matches!(self.peek(), 'e' | 'E') {
+                self.next_char();
+                if !self.complement('+') {
+                    self.complement('-');
+                }
+                groups.push(self.digits(10));
+            }
+        }
 
-            while self.peek().is_numeric() {
+        if is_xid_continue(self.peek()) {
+            while is_xid_continue(self.peek()) {
                 self.next_char();
             }
+            return Err(self.malformed_number("Invalid number literal"));
+        }
+        for group in groups.iter().map(|group| &self.source[group.clone()]) {
+            if group.is_empty() {
+                return Err(self.malformed_number("Invalid number literal"));
+            }
+            if group.starts_with('_') || group.ends_with('_') || group.contains("__") {
+                return Err(self.malformed_number("Digit separator must be between digits"));
+            }
         }
 
-        let value: f64 = self.source[self.start..self.current].parse().unwrap();
+        let digits = match radix {
+            10 => &self.source[self.start..self.current],
+            _ => &self.source[groups[0].clone()],
+        };
+        let digits = digits.replace('_', "");
+        let float = groups.len() > 1;
+        let value = match float {
+            true => digits
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .map(Number::Float),
+            // 2^63 only fits negated, which is left to 'Parser::min_integer'
+            false => match u64::from_str_radix(&digits, radix) {
+                Ok(value) if value == i64::MIN.unsigned_abs() => Some(Number::Int(i64::MIN)),
+                value => value
+                    .ok()
+                    .and_then(|value| i64::try_from(value).ok())
+                    .map(Number::Int),
+            },
+        };
+        let Some(value) = value else {
+            return Err(self.malformed_number("Number literal is too large"));
+        };
+
         self.add_token(TokenKind::Number(value));
         Ok(())
     }
 
-    fn string(&mut self) -> Result<(), InterpErr> {
-        while self.peek() != '"' && !self.finished() {
-            if self.peek() == '\n' {
-                self.line += 1
+    // consumes the digits of the radix and separators, returning where they are
+    fn digits(&mut self, radix: u32) -> Range<usize> {
+        let start = self.current;
+        while self.peek().is_digit(radix) || self.peek() == '_' {
+            self.next_char();
+        }
+        start..self.current
+    }
+
+    fn malformed_number(&self, msg: &str) -> InterpErr {
+        InterpErr::SyntaxError {
+            line: self.start_line,
+            span: self.span(),
+            msg: msg.to_string(),
+            place: self.source[self.start..self.current].to_string(),
+        }
+    }
+
+    // '"..."' with escapes, 'r"..."' without them. Either can be triple quoted
+    // to span multiple lines, see 'dedented'
+    fn string(&mut self, raw: bool) -> Result<(), InterpErr> {
+        let triple = self.peek() == '"' && self.peek_next() == '"';
+        if triple {
+            self.next_char();
+            self.next_char();
+        }
+        self.string_body(raw, triple, false)
+    }
+
+    // the rest of a string up to its closing quotes. Plain strings can embed
+    // expressions: the text up to a '${' becomes an 'Interpolated' token, the
+    // expression is lexed as usual and its closing '}' continues the string,
+    // until the last piece which is an 'InterpolatedEnd' token
+    fn string_body(&mut self, raw: bool, triple: bool, continued: bool) -> Result<(), InterpErr> {
+        let content_start = self.current;
+        let interpolates = !raw && !triple;
+        let mut interpolated = false;
+
+        loop {
+            if self.finished() {
+                return Err(InterpErr::SyntaxError {
+                    line: self.start_line,
+                    span: self.span(),
+                    msg: "Unterminated string".to_string(),
+                    place: "".to_string(),
+                });
+            }
+
+            let closing = match triple {
+                true => self.source[self.current..].starts_with("\"\"\""),
+                false => self.peek() == '"',
+            };
+            if closing {
+                break;
+            }
+            if interpolates && self.peek() == '$' && self.peek_next() == '{' {
+                interpolated = true;
+                break;
+            }
+
+            // the escaped character can't end the string
+            if self.peek() == '\\' && !raw {
+                self.next_char();
+            }
+            if !self.finished() {
+                self.next_char();
             }
+        }
+
+        let content_end = self.current;
+        let closing = match (interpolated, triple) {
+            (true, _) => 2,
+            (false, true) => 3,
+            (false, false) => 1,
+        };
+        for _ in 0..closing {
             self.next_char();
         }
 
-        if self.finished() {
-            return Err(InterpErr::SyntaxError {
-                line: self.line,
-                msg: "Unterminated string".to_string(),
-                place: "".to_string(),
-            });
+        let content = &self.source[content_start..content_end];
+        let lines = match triple {
+            true => dedented(content, content_start),
+            false => Vec::from([(content_start, content)]),
+        };
+
+        let mut value = String::new();
+        for (i, (offset, line)) in lines.into_iter().enumerate() {
+            if i > 0 {
+                value.push('\n');
+            }
+            match raw {
+                true => value.push_str(line),
+                false => self.unescape(line, offset, &mut value)?,
+            }
         }
 
-        self.next_char();
+        let kind = if interpolated {
+            self.interpolations.push(0);
+            TokenKind::Interpolated(value)
+        } else if continued {
+            TokenKind::InterpolatedEnd(value)
+        } else {
+            TokenKind::String(value)
+        };
+        self.add_token(kind);
+        Ok(())
+    }
 
-        let value = &self.source[self.start + 1..self.current - 1];
-        let lexeme = &self.source[self.start..self.current];
+    // 'text' starts at 'offset' in the source
+    fn unescape(&self, text: &str, offset: usize, value: &mut String) -> Result<(), InterpErr> {
+        let mut chars = text.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            if c != '\\' {
+                value.push(c);
+                continue;
+            }
 
-        self.tokens.push(Token::new(
-            TokenKind::String(value.to_string()),
-            lexeme.to_string(),
-            self.line,
-        ));
+            let escaped = match chars.next().map(|(_, c)| c) {
+                Some('n') => Some('\n'),
+                Some('t') => Some('\t'),
+                Some('r') => Some('\r'),
+                Some('0') => Some('\0'),
+                Some('\\') => Some('\\'),
+                Some('"') => Some('"'),
+                Some('$') => Some('$'),
+                Some('u') if chars.next_if(|&(_, c)| c == '{').is_some() => {
+                    let mut digits = String::new();
+                    while let Some((_, c)) = chars.next_if(|(_, c)| c.is_ascii_hexdigit()) {
+                        digits.push(c);
+                    }
+                    let closed = chars.next_if(|&(_, c)| c == '}').is_some();
+                    match closed && (1..=6).contains(&digits.len()) {
+                        true => char::from_u32(u32::from_str_radix(&digits, 16).unwrap()),
+                        false => None,
+                    }
+                }
+                _ => None,
+            };
+
+            match escaped {
+                Some(c) => value.push(c),
+                None => {
+                    let end = chars.peek().map_or(text.len(), |&(end, _)| end);
+                    let escape = &text[start..end];
+                    let line = self.start_line
+                        + self.source[self.start..offset + start]
+                            .matches('\n')
+                            .count();
+                    let msg = match escape.starts_with("\\u") {
+                        true => "Invalid unicode escape",
+                        false => "Invalid escape sequence",
+                    };
+                    return Err(InterpErr::SyntaxError {
+                        line,
+                        span: Span::new(offset + start, offset + end),
+                        msg: msg.to_string(),
+                        place: escape.to_string(),
+                    });
+                }
+            }
+        }
 
         Ok(())
     }
@@ -246,42 +606,85 @@ impl Lexer {
     }
 
     fn peek(&self) -> char {
-        if self.finished() {
-            return '\0';
-        }
-
-        self.source.chars().nth(self.current).unwrap()
+        self.source[self.current..].chars().next().unwrap_or('\0')
     }
 
     fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            return '\0';
-        }
-
-        self.source.chars().nth(self.current + 1).unwrap()
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
     }
 
     fn add_token(&mut self, ty: TokenKind) {
         let lexeme = &self.source[self.start..self.current];
-        let tk = Token::new(ty, lexeme.to_string(), self.line);
+        let tk = Token::new(
+            ty,
+            lexeme.to_string(),
+            self.start_line,
+            self.start_column,
+            self.span(),
+        );
         self.tokens.push(tk);
     }
 
+    // span of the token being lexed
+    fn span(&self) -> Span {
+        Span::new(self.start, self.current)
+    }
+
+    // the only way forward, so that the line and column follow along
     fn next_char(&mut self) -> char {
-        let char = self.source.chars().nth(self.current).unwrap();
-        self.current += 1;
+        let char = self.source[self.current..].chars().next().unwrap();
+        self.current += char.len_utf8();
+        if char == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
         char
     }
 
     fn complement(&mut self, expected: char) -> bool {
-        if self.finished() {
-            return false;
-        };
-        if self.source.chars().nth(self.current).unwrap() != expected {
+        if self.finished() || self.peek() != expected {
             return false;
         };
 
-        self.current += 1;
+        self.next_char();
         true
     }
 }
+
+// the lines of a triple quoted string, each with the offset where it starts in
+// the source. A line break right after the opening quotes isn't part of the
+// string, and neither is the line of the closing quotes when they are on their
+// own. The indentation common to every line, that line included, is stripped
+fn dedented(content: &str, offset: usize) -> Vec<(usize, &str)> {
+    let mut lines = Vec::new();
+    let mut start = offset;
+    for line in content.split('\n') {
+        lines.push((start, line));
+        start += line.len() + 1;
+    }
+
+    // only spaces and tabs indent, which also keeps the cuts on char boundaries
+    let indentation = |line: &str| line.len() - line.trim_start_matches([' ', '\t']).len();
+    let blank = |line: &str| indentation(line) == line.trim_end_matches('\r').len();
+
+    if lines.len() > 1 && blank(lines[0].1) {
+        lines.remove(0);
+    }
+    let mut indent = usize::MAX;
+    if lines.len() > 1 && blank(lines[lines.len() - 1].1) {
+        indent = lines.pop().unwrap().1.len();
+    }
+    for (_, line) in lines.iter().filter(|(_, line)| !blank(line)) {
+        indent = indent.min(indentation(line));
+    }
+
+    lines
+        .into_iter()
+        .map(|(start, line)| {
+            let cut = indent.min(indentation(line));
+            (start + cut, &line[cut..])
+        })
+        .collect()
+}
@@ -1,9 +1,17 @@
 pub mod ast;
+pub mod chunk;
+pub mod compiler;
+pub mod diagnostic;
 pub mod env;
 pub mod error;
 pub mod interp;
 pub mod lexer;
 pub mod loxstd;
+pub mod map;
+pub mod number;
 pub mod obj;
 pub mod parser;
+pub mod resolver;
+pub mod signature;
 pub mod token;
+pub mod vm;
@@ -1,22 +1,27 @@
 use crate::error::InterpErr;
 use crate::interp::Interpreter;
+use crate::map::{MapKey, OrderedMap};
+use crate::number::Number;
 use crate::obj::Callable;
 use crate::obj::LoxObject;
+use crate::signature::Signature;
+use crate::token::{Span, Token};
 use format as fmt;
+use std::{cell::RefCell, fmt::Display, rc::Rc};
 
 pub struct Clock {}
 impl Callable for Clock {
     fn call(&self, _: &mut Interpreter, _: Vec<LoxObject>) -> Result<LoxObject, InterpErr> {
-        Ok(LoxObject::Number(
+        Ok(LoxObject::Number(Number::Float(
             std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_secs() as f64,
-        ))
+        )))
     }
 
-    fn arity(&self) -> usize {
-        0
+    fn signature(&self) -> Signature {
+        Signature::fixed(&[])
     }
 
     fn to_string(&self) -> String {
@@ -31,12 +36,12 @@ impl Callable for Clock {
 pub struct Print {}
 impl Callable for Print {
     fn call(&self, _: &mut Interpreter, args: Vec<LoxObject>) -> Result<LoxObject, InterpErr> {
-        args.iter().for_each(|a| println!("{a}"));
+        print!("{}", print_line(&args)?);
         Ok(LoxObject::Null)
     }
 
-    fn arity(&self) -> usize {
-        1
+    fn signature(&self) -> Signature {
+        print_signature()
     }
 
     fn to_string(&self) -> String {
@@ -47,3 +52,266 @@ impl Callable for Print {
         Box::new(Print {})
     }
 }
+
+pub struct Eprint {}
+impl Callable for Eprint {
+    fn call(&self, _: &mut Interpreter, args: Vec<LoxObject>) -> Result<LoxObject, InterpErr> {
+        eprint!("{}", print_line(&args)?);
+        Ok(LoxObject::Null)
+    }
+
+    fn signature(&self) -> Signature {
+        print_signature()
+    }
+
+    fn to_string(&self) -> String {
+        "<native fn eprint>".to_string()
+    }
+
+    fn clone_box(&self) -> Box<dyn Callable> {
+        Box::new(Eprint {})
+    }
+}
+
+// 'print(values..., sep: " ", end: "\n")', the same for 'eprint'
+pub fn print_signature() -> Signature {
+    Signature::variadic(&[], &["sep", "end"])
+}
+
+// the values joined by the separator, followed by the end
+pub fn join_line<T: Display>(values: &[T], sep: Option<&str>, end: Option<&str>) -> String {
+    let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+    values.join(sep.unwrap_or(" ")) + end.unwrap_or("\n")
+}
+
+fn print_line(args: &[LoxObject]) -> Result<String, InterpErr> {
+    let (sep, end) = (
+        print_option(&args[0], "sep")?,
+        print_option(&args[1], "end")?,
+    );
+    Ok(join_line(&args[2..], sep, end))
+}
+
+fn print_option<'a>(arg: &'a LoxObject, name: &str) -> Result<Option<&'a str>, InterpErr> {
+    match arg {
+        LoxObject::Null => Ok(None),
+        LoxObject::Str(s) => Ok(Some(s.as_str())),
+        _ => Err(native_error(&fmt!("'{name}' must be a string"))),
+    }
+}
+
+pub struct Range {}
+impl Callable for Range {
+    fn call(&self, _: &mut Interpreter, args: Vec<LoxObject>) -> Result<LoxObject, InterpErr> {
+        match (&args[0], &args[1]) {
+            (LoxObject::Number(Number::Int(start)), LoxObject::Number(Number::Int(end))) => {
+                Ok(LoxObject::Range(*start, *end))
+            }
+            _ => Err(native_error("Range bounds must be integers")),
+        }
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::fixed(&["start", "end"])
+    }
+
+    fn to_string(&self) -> String {
+        "<native fn range>".to_string()
+    }
+
+    fn clone_box(&self) -> Box<dyn Callable> {
+        Box::new(Range {})
+    }
+}
+
+// name and parameters of every method lists have
+pub const LIST_METHODS: &[(&str, &[&str])] = &[
+    ("push", &["value"]),
+    ("pop", &[]),
+    ("len", &[]),
+    ("insert", &["index", "value"]),
+    ("remove", &["index"]),
+    ("slice", &["start", "end"]),
+];
+
+// name and parameters of every method maps have
+pub const MAP_METHODS: &[(&str, &[&str])] = &[
+    ("has", &["key"]),
+    ("remove", &["key"]),
+    ("keys", &[]),
+    ("values", &[]),
+    ("len", &[]),
+];
+
+// a method of a builtin type (list or map), bound to the value it was accessed on
+#[derive(Clone)]
+pub struct NativeMethod {
+    receiver: LoxObject,
+    name: Token,
+    params: &'static [&'static str],
+}
+
+impl NativeMethod {
+    pub fn bind(receiver: LoxObject, name: &Token) -> Result<LoxObject, InterpErr> {
+        let methods = match receiver {
+            LoxObject::Map(_) => MAP_METHODS,
+            _ => LIST_METHODS,
+        };
+
+        match methods.iter().find(|(method, _)| *method == name.lexeme) {
+            Some(&(_, params)) => Ok(LoxObject::Callable(Box::new(NativeMethod {
+                receiver,
+                name: name.clone(),
+                params,
+            }))),
+            None => Err(error(name, &fmt!("Undefined property '{}'", name.lexeme))),
+        }
+    }
+
+    fn list_call(
+        &self,
+        list: &mut Vec<LoxObject>,
+        args: Vec<LoxObject>,
+    ) -> Result<LoxObject, InterpErr> {
+        match self.name.lexeme.as_str() {
+            "push" => {
+                list.push(args[0].clone());
+                Ok(LoxObject::Null)
+            }
+            "pop" => list
+                .pop()
+                .ok_or_else(|| error(&self.name, "Can't pop from an empty list")),
+            "len" => Ok(LoxObject::Number(Number::Int(list.len() as i64))),
+            "insert" => {
+                let index = self.index(&args[0], list.len(), insert_index)?;
+                list.insert(index, args[1].clone());
+                Ok(LoxObject::Null)
+            }
+            "remove" => {
+                let index = self.index(&args[0], list.len(), list_index)?;
+                Ok(list.remove(index))
+            }
+            "slice" => match (&args[0], &args[1]) {
+                (LoxObject::Number(Number::Int(start)), LoxObject::Number(Number::Int(end))) => {
+                    let (start, end) = slice_bounds(*start, *end, list.len());
+                    let slice = list[start..end].to_vec();
+                    Ok(LoxObject::List(Rc::new(RefCell::new(slice))))
+                }
+                _ => Err(error(&self.name, "Slice bounds must be integers")),
+            },
+            _ => unreachable!("list methods are checked when bound"),
+        }
+    }
+
+    fn map_call(
+        &self,
+        map: &mut OrderedMap<LoxObject>,
+        args: Vec<LoxObject>,
+    ) -> Result<LoxObject, InterpErr> {
+        match self.name.lexeme.as_str() {
+            "has" => Ok(LoxObject::Bool(map.contains(&self.key(&args[0])?))),
+            "remove" => {
+                let key = self.key(&args[0])?;
+                map.remove(&key)
+                    .ok_or_else(|| error(&self.name, &fmt!("Undefined key '{}'", args[0])))
+            }
+            "keys" => {
+                let keys = map.keys().map(LoxObject::from).collect();
+                Ok(LoxObject::List(Rc::new(RefCell::new(keys))))
+            }
+            "values" => {
+                let values = map.values().cloned().collect();
+                Ok(LoxObject::List(Rc::new(RefCell::new(values))))
+            }
+            "len" => Ok(LoxObject::Number(Number::Int(map.len() as i64))),
+            _ => unreachable!("map methods are checked when bound"),
+        }
+    }
+
+    fn index(&self, index: &LoxObject, len: usize, slot: SlotFn) -> Result<usize, InterpErr> {
+        match index {
+            LoxObject::Number(Number::Int(n)) => {
+                slot(*n, len).map_err(|msg| error(&self.name, msg))
+            }
+            _ => Err(error(&self.name, "List index must be an integer")),
+        }
+    }
+
+    fn key(&self, key: &LoxObject) -> Result<MapKey, InterpErr> {
+        key.to_key().map_err(|msg| error(&self.name, msg))
+    }
+}
+
+impl Callable for NativeMethod {
+    fn call(&self, _: &mut Interpreter, args: Vec<LoxObject>) -> Result<LoxObject, InterpErr> {
+        match &self.receiver {
+            LoxObject::List(list) => self.list_call(&mut RefCell::borrow_mut(list), args),
+            LoxObject::Map(map) => self.map_call(&mut RefCell::borrow_mut(map), args),
+            _ => unreachable!("only lists and maps have native methods"),
+        }
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::fixed(self.params)
+    }
+
+    fn to_string(&self) -> String {
+        fmt!("<native fn {}>", self.name.lexeme)
+    }
+
+    fn clone_box(&self) -> Box<dyn Callable> {
+        Box::new(self.clone())
+    }
+}
+
+// turns an index into a slot of a list of the given length
+pub type SlotFn = fn(i64, usize) -> Result<usize, &'static str>;
+
+// negative indices count from the end of the list
+pub fn list_index(index: i64, len: usize) -> Result<usize, &'static str> {
+    let index = if index < 0 { index + len as i64 } else { index };
+    if index < 0 || index >= len as i64 {
+        return Err("List index out of bounds");
+    }
+
+    Ok(index as usize)
+}
+
+// the position right after the last element is also valid for insertion
+pub fn insert_index(index: i64, len: usize) -> Result<usize, &'static str> {
+    match index < 0 {
+        true => list_index(index, len),
+        false => list_index(index, len + 1),
+    }
+}
+
+// like indices, bounds can be negative. They are clamped to the list, so
+// slicing never fails, at worst the slice is empty
+pub fn slice_bounds(start: i64, end: i64, len: usize) -> (usize, usize) {
+    let clamp = |bound: i64| {
+        let bound = if bound < 0 { bound + len as i64 } else { bound };
+        bound.clamp(0, len as i64) as usize
+    };
+
+    let (start, end) = (clamp(start), clamp(end));
+    (start, end.max(start))
+}
+
+// natives don't know where they were called from, the call fills the position in
+fn native_error(msg: &str) -> InterpErr {
+    InterpErr::RuntimeError {
+        line: 0,
+        span: Span::default(),
+        msg: msg.to_string(),
+        trace: Vec::new(),
+    }
+}
+
+fn error(name: &Token, msg: &str) -> InterpErr {
+    InterpErr::RuntimeError {
+        line: name.line,
+        span: name.span,
+        msg: msg.to_string(),
+        trace: Vec::new(),
+    }
+}
@@ -1,31 +1,56 @@
-use interp::{error::InterpErr, interp::Interpreter, lexer::Lexer, parser::Parser};
+use interp::{
+    compiler::Compiler, diagnostic::Diagnostic, error::InterpErr, interp::Interpreter,
+    lexer::Lexer, parser::Parser, resolver::Resolver, vm::Vm,
+};
 use std::io::Write;
 
+// the same program can run on the tree walking interpreter or be compiled to
+// bytecode and executed by the virtual machine
+enum Backend {
+    TreeWalker(Interpreter),
+    Vm(Vm),
+}
+
+// the tree walker recurses on the native stack for every call it makes, the
+// thread it runs on has room for 'FRAMES_MAX' of them
+const STACK_SIZE: usize = 256 << 20;
+
 fn main() {
-    let args: Vec<_> = std::env::args().collect();
-    let mut interp = Interpreter::new();
+    let cli = std::thread::Builder::new().stack_size(STACK_SIZE);
+    cli.spawn(run_cli).unwrap().join().unwrap()
+}
+
+fn run_cli() {
+    let mut args: Vec<_> = std::env::args().skip(1).collect();
+
+    let mut backend = match args.iter().position(|a| a == "--vm") {
+        Some(i) => {
+            args.remove(i);
+            Backend::Vm(Vm::new())
+        }
+        None => Backend::TreeWalker(Interpreter::new()),
+    };
 
     match args.len() {
+        0 => repl(&mut backend),
         1 => {
-            if let Err(e) = repl(&mut interp) {
-                eprintln!("{e}");
-                std::process::exit(65)
-            }
-        }
-        2 => {
-            if let Err(e) = run_file(&args[1], &mut interp) {
-                eprintln!("{e}");
+            let source = read_file(&args[0]);
+            if let Err(errors) = run(&source, &mut backend) {
+                let diagnostic = Diagnostic::new(&args[0], &source);
+                errors
+                    .iter()
+                    .for_each(|e| eprint!("{}", diagnostic.render(e)));
                 std::process::exit(65)
             }
         }
         _ => {
-            eprintln!("Usage: twli [script]");
+            eprintln!("Usage: twli [--vm] [script]");
             std::process::exit(64)
         }
     }
 }
 
-fn repl(interp: &mut Interpreter) -> Result<(), InterpErr> {
+fn repl(backend: &mut Backend) {
     let mut input = String::new();
     let stdin = std::io::stdin();
 
@@ -37,26 +62,44 @@ fn repl(interp: &mut Interpreter) -> Result<(), InterpErr> {
         stdin.read_line(&mut input).unwrap();
 
         if input.trim() == "exit" {
-            return Ok(());
+            return;
         }
 
-        match run(&input, interp) {
-            Ok(_) => continue,
-            Err(e) => eprintln!("> {e}"),
+        if let Err(errors) = run(&input, backend) {
+            let diagnostic = Diagnostic::new("<repl>", &input);
+            errors
+                .iter()
+                .for_each(|e| eprint!("{}", diagnostic.render(e)));
         }
     }
 }
 
-fn run_file(path: &str, interp: &mut Interpreter) -> Result<(), InterpErr> {
+fn read_file(path: &str) -> String {
     let f = std::fs::read(path).unwrap();
-    run(&String::from_utf8(f).unwrap(), interp)
+    String::from_utf8(f).unwrap()
 }
 
-fn run(source: &str, interp: &mut Interpreter) -> Result<(), InterpErr> {
+// lexing and parsing report every error they find, the later stages stop at the first one
+fn run(source: &str, backend: &mut Backend) -> Result<(), Vec<InterpErr>> {
     let mut lexer = Lexer::new(source.to_string());
-    let tokens = lexer.tokenized()?;
+    let (tokens, mut errors) = lexer.scan();
     let mut parser = Parser::new(tokens);
-    let ast = parser.parse()?;
-    interp.interpret(ast)?;
-    Ok(())
+    let mut ast = match parser.parse() {
+        Ok(ast) if errors.is_empty() => ast,
+        Ok(_) => return Err(errors),
+        Err(parse_errors) => {
+            errors.extend(parse_errors);
+            errors.sort_by_key(|e| e.span().start);
+            return Err(errors);
+        }
+    };
+    Resolver::new().resolve(&mut ast).map_err(|e| vec![e])?;
+
+    let result = match backend {
+        Backend::TreeWalker(interp) => interp.interpret(ast),
+        Backend::Vm(vm) => Compiler::new()
+            .compile(&ast)
+            .and_then(|function| vm.interpret(function)),
+    };
+    result.map_err(|e| vec![e])
 }
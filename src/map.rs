@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use crate::number::Number;
+
+// MENTAL MODEL:
+// maps only accept keys that compare by value: numbers, strings, booleans and
+// null. A key is the hashable form of one of those values, built so that two
+// keys are equal exactly when the values are equal ('PartialEq for LoxObject'):
+// a float holding an integer is the same key as that integer (so 0 and -0 are
+// too), and NaN, which isn't even equal to itself, can't be a key at all.
+// Entries are kept in insertion order.
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Int(i64),
+    Float(u64), //bits of a float that isn't an integer
+    Str(String),
+    Bool(bool),
+    Null,
+}
+
+impl MapKey {
+    pub fn number(n: Number) -> Result<MapKey, &'static str> {
+        let n = match n {
+            Number::Int(n) => return Ok(MapKey::Int(n)),
+            Number::Float(n) => n,
+        };
+        if n.is_nan() {
+            return Err("NaN can't be used as a map key");
+        }
+
+        match Number::exact(n) {
+            Some(n) => Ok(MapKey::Int(n)),
+            None => Ok(MapKey::Float(n.to_bits())),
+        }
+    }
+}
+
+pub const KEY_ERROR: &str = "Map keys must be numbers, strings, booleans or null";
+
+#[derive(Clone)]
+pub struct OrderedMap<V> {
+    entries: Vec<(MapKey, V)>,
+    slots: HashMap<MapKey, usize>,
+}
+
+impl<V> Default for OrderedMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> OrderedMap<V> {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            slots: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, key: &MapKey) -> Option<&V> {
+        self.slots.get(key).map(|&slot| &self.entries[slot].1)
+    }
+
+    // a key that is already there keeps its position
+    pub fn insert(&mut self, key: MapKey, value: V) {
+        match self.slots.get(&key) {
+            Some(&slot) => self.entries[slot].1 = value,
+            None => {
+                self.slots.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &MapKey) -> Option<V> {
+        let slot = self.slots.remove(key)?;
+        let (_, value) = self.entries.remove(slot);
+
+        for (key, _) in &self.entries[slot..] {
+            if let Some(s) = self.slots.get_mut(key) {
+                *s -= 1;
+            }
+        }
+
+        Some(value)
+    }
+
+    pub fn contains(&self, key: &MapKey) -> bool {
+        self.slots.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(MapKey, V)> {
+        self.entries.iter()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &MapKey> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.iter().map(|(_, value)| value)
+    }
+}
+
+// same entries, in any order
+impl<V: PartialEq> PartialEq for OrderedMap<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
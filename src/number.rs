@@ -0,0 +1,194 @@
+use std::{cmp::Ordering, fmt::Display};
+
+// MENTAL MODEL:
+// a number is a 64 bit integer or a float. Arithmetic on two integers stays
+// exact and fails on overflow instead of wrapping around. As soon as a float is
+// involved the other operand is promoted and the result is a float. '/' always
+// divides exactly, so it makes a float, '//' is the integer division. Both
+// backends hold a 'Number' in their values, so the rules live here once.
+// Integers and floats compare by value: 1 == 1.0, and they are the same map key
+
+#[derive(Debug, Clone, Copy)]
+pub enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+// a binary operator on numbers
+pub type ArithmeticFn = fn(Number, Number) -> Result<Number, &'static str>;
+
+// a bitwise operator on integers, 'None' if the operands are out of its range
+pub type IntegerFn = fn(i64, i64) -> Option<i64>;
+
+const OVERFLOW: &str = "Integer overflow";
+
+impl Number {
+    pub fn float(self) -> f64 {
+        match self {
+            Number::Int(n) => n as f64,
+            Number::Float(n) => n,
+        }
+    }
+
+    // the integer a float holds exactly, if any
+    pub fn exact(n: f64) -> Option<i64> {
+        let in_range = (i64::MIN as f64..-(i64::MIN as f64)).contains(&n);
+        (n.fract() == 0.0 && in_range).then_some(n as i64)
+    }
+
+    pub fn negate(self) -> Result<Number, &'static str> {
+        match self {
+            Number::Int(n) => n.checked_neg().map(Number::Int).ok_or(OVERFLOW),
+            Number::Float(n) => Ok(Number::Float(-n)),
+        }
+    }
+
+    // bitwise operators take integers, and the floats holding one exactly
+    pub fn integer(self) -> Option<i64> {
+        match self {
+            Number::Int(n) => Some(n),
+            Number::Float(n) => Number::exact(n),
+        }
+    }
+
+    pub fn bit_not(self) -> Result<Number, &'static str> {
+        match self.integer() {
+            Some(n) => Ok(Number::Int(!n)),
+            None => Err("Operand must be an integer"),
+        }
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        match (*self, *other) {
+            (Number::Int(a), Number::Int(b)) => a == b,
+            (Number::Float(a), Number::Float(b)) => a == b,
+            (Number::Int(i), Number::Float(f)) | (Number::Float(f), Number::Int(i)) => {
+                Number::exact(f) == Some(i)
+            }
+        }
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (*self, *other) {
+            (Number::Int(a), Number::Int(b)) => Some(a.cmp(&b)),
+            (a, b) => a.float().partial_cmp(&b.float()),
+        }
+    }
+}
+
+// floats always show a '.' or an exponent, so they never look like integers
+impl Display for Number {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Number::Int(n) => write!(f, "{n}"),
+            Number::Float(n) => write!(f, "{n:?}"),
+        }
+    }
+}
+
+// integers stay integers while the result fits, a float on either side makes a float
+fn arithmetic(
+    l: Number,
+    r: Number,
+    int: IntegerFn,
+    float: fn(f64, f64) -> f64,
+) -> Result<Number, &'static str> {
+    match (l, r) {
+        (Number::Int(l), Number::Int(r)) => int(l, r).map(Number::Int).ok_or(OVERFLOW),
+        _ => Ok(Number::Float(float(l.float(), r.float()))),
+    }
+}
+
+// floats divide by zero into infinity or NaN, integers can't
+fn nonzero(l: Number, r: Number) -> Result<(), &'static str> {
+    match (l, r) {
+        (Number::Int(_), Number::Int(0)) => Err("Division by zero"),
+        _ => Ok(()),
+    }
+}
+
+pub fn add(l: Number, r: Number) -> Result<Number, &'static str> {
+    arithmetic(l, r, i64::checked_add, |l, r| l + r)
+}
+
+pub fn subtract(l: Number, r: Number) -> Result<Number, &'static str> {
+    arithmetic(l, r, i64::checked_sub, |l, r| l - r)
+}
+
+pub fn multiply(l: Number, r: Number) -> Result<Number, &'static str> {
+    arithmetic(l, r, i64::checked_mul, |l, r| l * r)
+}
+
+pub fn divide(l: Number, r: Number) -> Result<Number, &'static str> {
+    Ok(Number::Float(l.float() / r.float()))
+}
+
+pub fn floor_divide(l: Number, r: Number) -> Result<Number, &'static str> {
+    nonzero(l, r)?;
+    arithmetic(l, r, floor_div, |l, r| (l / r).floor())
+}
+
+pub fn modulo(l: Number, r: Number) -> Result<Number, &'static str> {
+    nonzero(l, r)?;
+    arithmetic(l, r, floor_mod_int, floor_mod)
+}
+
+// a negative integer exponent makes a fraction, so a float
+pub fn power(l: Number, r: Number) -> Result<Number, &'static str> {
+    match (l, r) {
+        (Number::Int(l), Number::Int(r)) if r >= 0 => u32::try_from(r)
+            .ok()
+            .and_then(|r| l.checked_pow(r))
+            .map(Number::Int)
+            .ok_or(OVERFLOW),
+        _ => Ok(Number::Float(l.float().powf(r.float()))),
+    }
+}
+
+fn floor_div(l: i64, r: i64) -> Option<i64> {
+    let q = l.checked_div(r)?;
+    if l % r != 0 && (l < 0) != (r < 0) {
+        return Some(q - 1);
+    }
+    Some(q)
+}
+
+fn floor_mod_int(l: i64, r: i64) -> Option<i64> {
+    let m = l.checked_rem(r)?;
+    if m != 0 && (m < 0) != (r < 0) {
+        return Some(m + r);
+    }
+    Some(m)
+}
+
+// modulo with the sign of the divisor, so that 'a == (a // b) * b + a % b'
+fn floor_mod(l: f64, r: f64) -> f64 {
+    let m = l % r;
+    if m != 0.0 && (m < 0.0) != (r < 0.0) {
+        return m + r;
+    }
+    m
+}
+
+// see 'Number::integer'
+pub fn integer_op(l: Number, r: Number, op: IntegerFn) -> Result<Number, &'static str> {
+    let (Some(l), Some(r)) = (l.integer(), r.integer()) else {
+        return Err("Operands must be integers");
+    };
+    match op(l, r) {
+        Some(n) => Ok(Number::Int(n)),
+        None => Err("Shift amount must be between 0 and 63"),
+    }
+}
+
+pub fn shift_left(l: i64, r: i64) -> Option<i64> {
+    l.checked_shl(u32::try_from(r).ok()?)
+}
+
+pub fn shift_right(l: i64, r: i64) -> Option<i64> {
+    l.checked_shr(u32::try_from(r).ok()?)
+}
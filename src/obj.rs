@@ -1,64 +1,162 @@
-use crate::{ast::FnStmt, env::Environment, error::InterpErr, interp::Interpreter};
+use crate::{
+    ast::FnStmt,
+    env::Environment,
+    error::{CaughtError, InterpErr},
+    interp::{Flow, Interpreter},
+    map::{MapKey, OrderedMap, KEY_ERROR},
+    number::Number,
+    signature::{Bound, Signature},
+    token::Token,
+};
 use std::{
     cell::RefCell,
+    collections::HashMap,
     fmt::{Debug, Display},
     rc::Rc,
 };
 
 pub trait Callable {
     fn call(&self, interp: &mut Interpreter, args: Vec<LoxObject>) -> Result<LoxObject, InterpErr>;
-    fn arity(&self) -> usize;
+    // called with the arguments already matched to 'signature'. Natives get
+    // null for the parameters left out, followed by the extra arguments
+    fn call_bound(
+        &self,
+        interp: &mut Interpreter,
+        args: Bound<LoxObject>,
+    ) -> Result<LoxObject, InterpErr> {
+        self.call(interp, args.into_values(LoxObject::Null))
+    }
+    fn signature(&self) -> Signature;
     fn to_string(&self) -> String;
     fn clone_box(&self) -> Box<dyn Callable>;
 }
 
 pub enum LoxObject {
-    Number(f64),
+    Number(Number),
     Str(String),
     Bool(bool),
     Null,
     Callable(Box<dyn Callable>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+    List(Rc<RefCell<Vec<LoxObject>>>),
+    Map(Rc<RefCell<OrderedMap<LoxObject>>>),
+    Range(i64, i64), //integers from the start (inclusive) to the end (exclusive)
+    Error(Rc<CaughtError>),
+}
+
+impl LoxObject {
+    // only values compared by value can be map keys
+    pub fn to_key(&self) -> Result<MapKey, &'static str> {
+        match self {
+            LoxObject::Number(n) => MapKey::number(*n),
+            LoxObject::Str(s) => Ok(MapKey::Str(s.clone())),
+            LoxObject::Bool(b) => Ok(MapKey::Bool(*b)),
+            LoxObject::Null => Ok(MapKey::Null),
+            _ => Err(KEY_ERROR),
+        }
+    }
+}
+
+impl From<&MapKey> for LoxObject {
+    fn from(key: &MapKey) -> Self {
+        match key {
+            MapKey::Int(n) => LoxObject::Number(Number::Int(*n)),
+            MapKey::Float(bits) => LoxObject::Number(Number::Float(f64::from_bits(*bits))),
+            MapKey::Str(s) => LoxObject::Str(s.clone()),
+            MapKey::Bool(b) => LoxObject::Bool(*b),
+            MapKey::Null => LoxObject::Null,
+        }
+    }
 }
 
+#[derive(Clone)]
 pub struct LoxFunction {
     closure: Rc<RefCell<Environment>>,
     declaration: FnStmt,
+    is_initializer: bool,
 }
 
 impl LoxFunction {
-    pub fn new(declaration: FnStmt, closure: Rc<RefCell<Environment>>) -> Self {
+    pub fn new(
+        declaration: FnStmt,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> Self {
         Self {
             declaration,
             closure,
+            is_initializer,
         }
     }
+
+    // creates a copy of the method whose closure has 'this' bound to the instance
+    pub fn bind(&self, instance: Rc<RefCell<LoxInstance>>) -> LoxFunction {
+        let mut env = Environment::new(Some(Rc::clone(&self.closure)));
+        env.define("this", LoxObject::Instance(instance));
+        LoxFunction::new(
+            self.declaration.clone(),
+            Rc::new(RefCell::new(env)),
+            self.is_initializer,
+        )
+    }
+
+    // an initializer always gives back the instance it was bound to,
+    // which is the only value in the environment created by 'bind'
+    fn this(&self) -> LoxObject {
+        RefCell::borrow(&self.closure).get_at(0, 0)
+    }
 }
 
 impl Callable for LoxFunction {
     fn call(&self, interp: &mut Interpreter, args: Vec<LoxObject>) -> Result<LoxObject, InterpErr> {
-        let mut env = Environment::new(Some(Rc::clone(&self.closure)));
+        let slots = args.into_iter().map(Some).collect();
+        self.call_bound(
+            interp,
+            Bound {
+                slots,
+                rest: Vec::new(),
+            },
+        )
+    }
+
+    // parameters left out are defined from their default, evaluated in the new
+    // environment so it sees the parameters before it
+    fn call_bound(
+        &self,
+        interp: &mut Interpreter,
+        args: Bound<LoxObject>,
+    ) -> Result<LoxObject, InterpErr> {
+        let env = Rc::new(RefCell::new(Environment::new(Some(Rc::clone(
+            &self.closure,
+        )))));
 
-        for i in 0..self.declaration.params.len() {
-            env.define(&self.declaration.params[i].lexeme, args[i].clone());
+        let Bound { slots, mut rest } = args;
+        let mut slots = slots.into_iter();
+        for param in &self.declaration.params {
+            let value = match (param.rest, slots.next().flatten(), &param.default) {
+                (true, _, _) => LoxObject::List(Rc::new(RefCell::new(std::mem::take(&mut rest)))),
+                (false, Some(value), _) => value,
+                (false, None, Some(default)) => interp.evaluate_in(default, Rc::clone(&env))?,
+                (false, None, None) => LoxObject::Null,
+            };
+            RefCell::borrow_mut(&env).define(&param.ident.lexeme, value);
         }
 
-        match interp.block_stmt_exec(
-            self.declaration.body.iter().collect(),
-            Rc::new(RefCell::new(env)),
-        ) {
-            Ok(_) => Ok(LoxObject::Null),
-            Err(err) => match err {
-                InterpErr::Return { value } => match value {
-                    Some(v) => Ok(v),
-                    None => Ok(LoxObject::Null),
-                },
-                _ => Err(err),
-            },
+        let result = match interp.block_stmt_exec(self.declaration.body.iter().collect(), env)? {
+            Flow::Return(value) => value,
+            _ => LoxObject::Null,
+        };
+
+        if self.is_initializer {
+            return Ok(self.this());
         }
+
+        Ok(result)
     }
 
-    fn arity(&self) -> usize {
-        self.declaration.params.len()
+    fn signature(&self) -> Signature {
+        Signature::of(&self.declaration)
     }
 
     fn to_string(&self) -> String {
@@ -66,10 +164,116 @@ impl Callable for LoxFunction {
     }
 
     fn clone_box(&self) -> Box<dyn Callable> {
-        Box::new(LoxFunction::new(
-            self.declaration.clone(),
-            Rc::clone(&self.closure),
-        ))
+        Box::new(self.clone())
+    }
+}
+
+pub struct LoxClass {
+    pub name: String,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<String, LoxFunction>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: String,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<String, LoxFunction>,
+    ) -> Self {
+        Self {
+            name,
+            superclass,
+            methods,
+        }
+    }
+
+    // looks for the method in the class itself and then up the superclass chain
+    pub fn find_method(&self, name: &str) -> Option<&LoxFunction> {
+        match self.methods.get(name) {
+            Some(method) => Some(method),
+            None => self.superclass.as_ref()?.find_method(name),
+        }
+    }
+}
+
+// calling a class creates a new instance and runs 'init' on it, if there is one
+impl Callable for Rc<LoxClass> {
+    fn call(&self, interp: &mut Interpreter, args: Vec<LoxObject>) -> Result<LoxObject, InterpErr> {
+        let instance = Rc::new(RefCell::new(LoxInstance::new(Rc::clone(self))));
+
+        if let Some(init) = self.find_method("init") {
+            init.bind(Rc::clone(&instance)).call(interp, args)?;
+        }
+
+        Ok(LoxObject::Instance(instance))
+    }
+
+    fn call_bound(
+        &self,
+        interp: &mut Interpreter,
+        args: Bound<LoxObject>,
+    ) -> Result<LoxObject, InterpErr> {
+        let instance = Rc::new(RefCell::new(LoxInstance::new(Rc::clone(self))));
+
+        if let Some(init) = self.find_method("init") {
+            init.bind(Rc::clone(&instance)).call_bound(interp, args)?;
+        }
+
+        Ok(LoxObject::Instance(instance))
+    }
+
+    fn signature(&self) -> Signature {
+        match self.find_method("init") {
+            Some(init) => init.signature(),
+            None => Signature::fixed(&[]),
+        }
+    }
+
+    fn to_string(&self) -> String {
+        format!("<class {}>", self.name)
+    }
+
+    fn clone_box(&self) -> Box<dyn Callable> {
+        Box::new(Rc::clone(self))
+    }
+}
+
+pub struct LoxInstance {
+    class: Rc<LoxClass>,
+    fields: HashMap<String, LoxObject>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        Self {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    // fields shadow methods. Methods are bound to the instance when accessed
+    pub fn get(instance: &Rc<RefCell<LoxInstance>>, ident: &Token) -> Result<LoxObject, InterpErr> {
+        let this = RefCell::borrow(instance);
+
+        if let Some(field) = this.fields.get(&ident.lexeme) {
+            return Ok(field.clone());
+        }
+
+        if let Some(method) = this.class.find_method(&ident.lexeme) {
+            let bound = method.bind(Rc::clone(instance));
+            return Ok(LoxObject::Callable(Box::new(bound)));
+        }
+
+        Err(InterpErr::RuntimeError {
+            line: ident.line,
+            span: ident.span,
+            msg: format!("Undefined property '{}'", ident.lexeme),
+            trace: Vec::new(),
+        })
+    }
+
+    pub fn set(&mut self, ident: &Token, value: LoxObject) {
+        self.fields.insert(ident.lexeme.clone(), value);
     }
 }
 
@@ -87,6 +291,12 @@ impl PartialEq for LoxObject {
             (LoxObject::Bool(a), LoxObject::Bool(b)) => a == b,
             (LoxObject::Null, LoxObject::Null) => true,
             (LoxObject::Callable(_), LoxObject::Callable(_)) => false,
+            (LoxObject::Class(a), LoxObject::Class(b)) => Rc::ptr_eq(a, b),
+            (LoxObject::Instance(a), LoxObject::Instance(b)) => Rc::ptr_eq(a, b),
+            (LoxObject::List(a), LoxObject::List(b)) => *RefCell::borrow(a) == *RefCell::borrow(b),
+            (LoxObject::Map(a), LoxObject::Map(b)) => *RefCell::borrow(a) == *RefCell::borrow(b),
+            (LoxObject::Range(a, b), LoxObject::Range(c, d)) => a == c && b == d,
+            (LoxObject::Error(a), LoxObject::Error(b)) => Rc::ptr_eq(a, b),
             _ => false,
         }
     }
@@ -100,6 +310,12 @@ impl Clone for LoxObject {
             LoxObject::Bool(b) => LoxObject::Bool(*b),
             LoxObject::Null => LoxObject::Null,
             LoxObject::Callable(c) => LoxObject::Callable(c.clone()),
+            LoxObject::Class(c) => LoxObject::Class(Rc::clone(c)),
+            LoxObject::Instance(i) => LoxObject::Instance(Rc::clone(i)),
+            LoxObject::List(l) => LoxObject::List(Rc::clone(l)),
+            LoxObject::Map(m) => LoxObject::Map(Rc::clone(m)),
+            LoxObject::Range(start, end) => LoxObject::Range(*start, *end),
+            LoxObject::Error(e) => LoxObject::Error(Rc::clone(e)),
         }
     }
 }
@@ -112,6 +328,12 @@ impl Display for LoxObject {
             LoxObject::Null => format!("null"),
             LoxObject::Bool(b) => format!("{b}"),
             LoxObject::Callable(c) => format!("{}", c.to_string()),
+            LoxObject::Class(c) => c.to_string(),
+            LoxObject::Instance(i) => format!("<{} instance>", RefCell::borrow(i).class.name),
+            LoxObject::List(l) => list_to_string(&RefCell::borrow(l)),
+            LoxObject::Map(m) => map_to_string(&RefCell::borrow(m)),
+            LoxObject::Range(start, end) => format!("range({start}, {end})"),
+            LoxObject::Error(e) => e.msg.clone(),
         };
         write!(f, "{msg}")
     }
@@ -125,7 +347,26 @@ impl Debug for LoxObject {
             LoxObject::Null => format!("null"),
             LoxObject::Bool(b) => format!("{b}"),
             LoxObject::Callable(c) => format!("{}", c.to_string()),
+            LoxObject::Class(c) => c.to_string(),
+            LoxObject::Instance(i) => format!("<{} instance>", RefCell::borrow(i).class.name),
+            LoxObject::List(l) => list_to_string(&RefCell::borrow(l)),
+            LoxObject::Map(m) => map_to_string(&RefCell::borrow(m)),
+            LoxObject::Range(start, end) => format!("range({start}, {end})"),
+            LoxObject::Error(e) => e.msg.clone(),
         };
         write!(f, "{msg}")
     }
 }
+
+fn list_to_string(elements: &[LoxObject]) -> String {
+    let elements: Vec<_> = elements.iter().map(|e| e.to_string()).collect();
+    format!("[{}]", elements.join(", "))
+}
+
+fn map_to_string(map: &OrderedMap<LoxObject>) -> String {
+    let entries: Vec<_> = map
+        .iter()
+        .map(|(key, value)| format!("{}: {value}", LoxObject::from(key)))
+        .collect();
+    format!("{{{}}}", entries.join(", "))
+}
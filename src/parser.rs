@@ -1,35 +1,87 @@
 use crate::ast::{
-    Assign, Binary, Call, Expr, ExprStmt, FnStmt, IfStmt, LetStmt, Literal, Logical, ReturnStmt,
-    Stmt, Unary, WhileStmt,
+    Assign, Binary, BreakStmt, Call, ClassStmt, Conditional, ContinueStmt, Expr, ExprStmt, FnStmt,
+    ForStmt, Get, Grouping, IfStmt, Index, Interpolation, Lambda, LetStmt, ListExpr, Lit, Literal,
+    Logical, MapExpr, Param, ReturnStmt, Set, SetIndex, Stmt, SuperExpr, ThrowStmt, TryStmt, Unary,
+    Update, Variable, WhileStmt,
 };
 use crate::error::InterpErr;
 use crate::error::InterpErr as Ie;
-use crate::token::Token;
+use crate::number::Number;
 use crate::token::TokenKind as Tk;
 use crate::token::TokenKind;
+use crate::token::{Span, Token};
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    errors: Vec<InterpErr>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            errors: Vec::new(),
+        }
     }
 
     // A program is a list of statements. So parsing the list of tokens
-    // is generating a list os statements
-    pub fn parse(&mut self) -> Result<Vec<Stmt>, InterpErr> {
+    // is generating a list os statements. Parsing goes on after an error,
+    // so all of them can be reported at once
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<InterpErr>> {
         let mut statements = Vec::new();
 
         while !self.finished() {
-            statements.push(self.declaration()?);
+            if let Some(stmt) = self.recovering_declaration() {
+                statements.push(stmt);
+            }
+        }
+
+        if !self.errors.is_empty() {
+            return Err(std::mem::take(&mut self.errors));
         }
 
         Ok(statements)
     }
 
+    // records the error of a failed declaration and skips to the next one
+    fn recovering_declaration(&mut self) -> Option<Stmt> {
+        match self.declaration() {
+            Ok(stmt) => Some(stmt),
+            Err(e) => {
+                self.errors.push(e);
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    // discards tokens until a statement boundary: right after a ';'
+    // or right before a keyword that starts a statement
+    fn synchronize(&mut self) {
+        self.next_token();
+
+        while !self.finished() {
+            if let Tk::Semicolon = self.previous().kind {
+                return;
+            }
+
+            match self.peek().kind {
+                Tk::Class
+                | Tk::Fn
+                | Tk::Let
+                | Tk::For
+                | Tk::If
+                | Tk::While
+                | Tk::Return
+                | Tk::Try
+                | Tk::Throw => return,
+                _ => self.next_token(),
+            };
+        }
+    }
+
     // Declarations are statements that declare names (variables, functions, classes)
     fn declaration(&mut self) -> Result<Stmt, InterpErr> {
         if let Tk::Let = self.peek().kind {
@@ -38,9 +90,54 @@ impl Parser {
             return self.let_declaration();
         }
 
+        if let Tk::Class = self.peek().kind {
+            //Consumes the 'class' keyword
+            self.next_token();
+            return self.class_declaration();
+        }
+
+        // 'fn (' starts a function expression instead
+        if let (Tk::Fn, Tk::Identifier) = (&self.peek().kind, &self.peek_next().kind) {
+            //consues the 'fn' token
+            self.next_token();
+            return self.fn_statement();
+        }
+
         self.statement()
     }
 
+    fn class_declaration(&mut self) -> Result<Stmt, InterpErr> {
+        let ident = self.expect(Tk::Identifier, "Expected class name")?;
+
+        let mut superclass = None;
+        if let Tk::Less = self.peek().kind {
+            //consumes the '<' token
+            self.next_token();
+            let parent = self.expect(Tk::Identifier, "Expected superclass name")?;
+            if parent.lexeme == ident.lexeme {
+                return Err(Ie::SyntaxError {
+                    line: parent.line,
+                    span: parent.span,
+                    msg: "A class can't inherit from itself".to_string(),
+                    place: parent.lexeme,
+                });
+            }
+            let span = parent.span;
+            superclass = Some(Expr::Var(Variable::new(parent, None, span)));
+        }
+
+        self.expect(Tk::LeftBrace, "Expected '{' before class body")?;
+
+        // methods are declared like functions, but without the 'fn' keyword
+        let mut methods = Vec::new();
+        while !matches!(self.peek().kind, Tk::RightBrace) && !self.finished() {
+            methods.push(self.function()?);
+        }
+
+        self.expect(Tk::RightBrace, "Expected '}' after class body")?;
+        Ok(Stmt::ClassStmt(ClassStmt::new(ident, superclass, methods)))
+    }
+
     fn let_declaration(&mut self) -> Result<Stmt, InterpErr> {
         let ident = self.next_token().clone();
         match ident.kind {
@@ -54,14 +151,20 @@ impl Parser {
                 self.expect(Tk::Semicolon, "Expect ';' after declaration")?;
                 Ok(Stmt::LetStmt(LetStmt::new(ident, init)))
             }
-            _ => Err(InterpErr::RuntimeError {
+            _ => Err(InterpErr::SyntaxError {
                 line: ident.line,
+                span: ident.span,
                 msg: "Expected identifier".to_string(),
+                place: ident.lexeme,
             }),
         }
     }
 
     fn statement(&mut self) -> Result<Stmt, InterpErr> {
+        if let (Tk::Identifier, Tk::Colon) = (&self.peek().kind, &self.peek_next().kind) {
+            return self.labelled_statement();
+        }
+
         if let Tk::LeftBrace = self.peek().kind {
             //consumes the '{' token;
             self.next_token();
@@ -77,28 +180,81 @@ impl Parser {
         if let Tk::While = self.peek().kind {
             //consumes the 'while' token
             self.next_token();
-            return self.while_statement();
+            return self.while_statement(None);
         }
 
         if let Tk::For = self.peek().kind {
             //consumes the 'for' token
             self.next_token();
-            return self.for_statement();
+            return self.for_statement(None);
         }
 
-        if let Tk::Fn = self.peek().kind {
-            //consues the 'fn' token
-            self.next_token();
-            return self.fn_statement();
+        if let Tk::Break | Tk::Continue = self.peek().kind {
+            return self.jump_statement();
+        }
+
+        // a function declared as the body of an 'if' or a loop would only exist
+        // when that body runs, so it has to go in a block
+        if let (Tk::Fn, Tk::Identifier) = (&self.peek().kind, &self.peek_next().kind) {
+            let keyword = self.peek().clone();
+            return Err(Ie::SyntaxError {
+                line: keyword.line,
+                span: keyword.span,
+                msg: "Function declarations must be inside a block".to_string(),
+                place: keyword.lexeme,
+            });
         }
 
         if let Tk::Return = self.peek().kind {
             return self.return_statement();
         }
 
+        if let Tk::Try = self.peek().kind {
+            return self.try_statement();
+        }
+
+        if let Tk::Throw = self.peek().kind {
+            return self.throw_statement();
+        }
+
         self.expr_statement()
     }
 
+    // 'label: loop', so 'break label' and 'continue label' can refer to the loop
+    fn labelled_statement(&mut self) -> Result<Stmt, InterpErr> {
+        let label = self.next_token().clone();
+        //consumes the ':' token
+        self.next_token();
+
+        match self.next_token().kind {
+            Tk::While => self.while_statement(Some(label)),
+            Tk::For => self.for_statement(Some(label)),
+            _ => Err(Ie::SyntaxError {
+                line: label.line,
+                span: label.span,
+                msg: "Only loops can be labelled".to_string(),
+                place: label.lexeme,
+            }),
+        }
+    }
+
+    fn jump_statement(&mut self) -> Result<Stmt, InterpErr> {
+        let keyword = self.next_token().clone();
+
+        let mut label = None;
+        if let Tk::Identifier = self.peek().kind {
+            label = Some(self.next_token().clone());
+        }
+
+        let stmt = match keyword.kind {
+            Tk::Break => Stmt::BreakStmt(BreakStmt::new(keyword, label)),
+            _ => Stmt::ContinueStmt(ContinueStmt::new(keyword, label)),
+        };
+
+        self.expect(Tk::Semicolon, "Expected ';' after loop jump")?;
+        Ok(stmt)
+    }
+
     fn return_statement(&mut self) -> Result<Stmt, InterpErr> {
         let kw = self.next_token().clone();
         let mut value = None;
@@ -112,30 +268,199 @@ impl Parser {
         Ok(Stmt::ReturnStmt(ReturnStmt::new(kw, value)))
     }
 
+    // 'try { } catch (e) { } finally { }', where either clause can be left out
+    fn try_statement(&mut self) -> Result<Stmt, InterpErr> {
+        let kw = self.next_token().clone();
+        let body = self.block_statement("Expected '{' after 'try'")?;
+
+        let mut catch_clause = None;
+        if let Tk::Catch = self.peek().kind {
+            //consumes the 'catch' token
+            self.next_token();
+            self.expect(Tk::LeftParen, "Expected '(' after 'catch'")?;
+            let ident = self.expect(Tk::Identifier, "Expected identifier")?;
+            self.expect(Tk::RightParen, "Expected ')' after catch variable")?;
+            catch_clause = Some((
+                ident,
+                self.block_statement("Expected '{' after catch variable")?,
+            ));
+        }
+
+        let mut finally_body = None;
+        if let Tk::Finally = self.peek().kind {
+            //consumes the 'finally' token
+            self.next_token();
+            finally_body = Some(self.block_statement("Expected '{' after 'finally'")?);
+        }
+
+        if catch_clause.is_none() && finally_body.is_none() {
+            return Err(Ie::SyntaxError {
+                line: kw.line,
+                span: kw.span,
+                msg: "Expected 'catch' or 'finally' after try block".to_string(),
+                place: kw.lexeme,
+            });
+        }
+
+        Ok(Stmt::TryStmt(TryStmt::new(
+            kw,
+            body,
+            catch_clause,
+            finally_body,
+        )))
+    }
+
+    fn throw_statement(&mut self) -> Result<Stmt, InterpErr> {
+        let kw = self.next_token().clone();
+        let value = self.expression()?;
+        self.expect(Tk::Semicolon, "Expected ';' after throw statement")?;
+        Ok(Stmt::ThrowStmt(ThrowStmt::new(kw, value)))
+    }
+
+    fn block_statement(&mut self, msg: &str) -> Result<Box<Stmt>, InterpErr> {
+        self.expect(Tk::LeftBrace, msg)?;
+        Ok(Box::new(Stmt::Block(self.block()?)))
+    }
+
     fn fn_statement(&mut self) -> Result<Stmt, InterpErr> {
+        Ok(Stmt::FnStmt(self.function()?))
+    }
+
+    fn function(&mut self) -> Result<FnStmt, InterpErr> {
         let ident = self.expect(Tk::Identifier, "Expected identifier")?;
         self.expect(Tk::LeftParen, "Expected '(' after function identifier")?;
+        let params = self.parameters()?;
+        self.expect(Tk::LeftBrace, "Expected '{' before function body")?;
+        let body = self.block()?;
+        Ok(FnStmt::new(ident, params, body))
+    }
 
-        let mut params = Vec::new();
-        while !matches!(self.peek().kind, Tk::RightParen) {
-            if params.len() > 255 {
-                return Err(InterpErr::RuntimeError {
+    // the parameters after the '(', up to the closing ')'. Parameters with a
+    // default come after the ones without, and a rest parameter comes last
+    fn parameters(&mut self) -> Result<Vec<Param>, InterpErr> {
+        let mut params: Vec<Param> = Vec::new();
+        while !matches!(self.peek().kind, Tk::RightParen) && !self.finished() {
+            if params.len() >= 255 {
+                return Err(InterpErr::SyntaxError {
                     line: self.peek().line,
+                    span: self.peek().span,
                     msg: "Cant have more than 255 parameters".to_string(),
+                    place: self.peek().lexeme.clone(),
                 });
             }
-            params.push(self.next_token().clone());
+            if params.last().is_some_and(|param| param.rest) {
+                return Err(InterpErr::SyntaxError {
+                    line: self.peek().line,
+                    span: self.peek().span,
+                    msg: "Rest parameter must be last".to_string(),
+                    place: self.peek().lexeme.clone(),
+                });
+            }
+
+            let rest = matches!(self.peek().kind, Tk::Ellipsis);
+            if rest {
+                //consumes the '...' token
+                self.next_token();
+            }
+            let ident = self.expect(Tk::Identifier, "Expected parameter name")?;
+
+            let mut default = None;
+            if rest && Tk::Equal == self.peek().kind {
+                return Err(InterpErr::SyntaxError {
+                    line: ident.line,
+                    span: ident.span,
+                    msg: "Rest parameter can't have a default".to_string(),
+                    place: ident.lexeme,
+                });
+            } else if Tk::Equal == self.peek().kind {
+                //consumes the '=' token
+                self.next_token();
+                default = Some(self.expression()?);
+            } else if !rest && params.iter().any(|param| param.default.is_some()) {
+                return Err(InterpErr::SyntaxError {
+                    line: ident.line,
+                    span: ident.span,
+                    msg: "Expected default value for parameter after one with a default"
+                        .to_string(),
+                    place: ident.lexeme,
+                });
+            }
+            params.push(Param::new(ident, default, rest));
+
             if Tk::Comma == self.peek().kind {
                 self.next_token();
             }
         }
         self.expect(Tk::RightParen, "Expected ')' after paremeters")?;
+        Ok(params)
+    }
+
+    // 'fn (params) { body }'
+    fn function_expr(&mut self) -> Result<Expr, InterpErr> {
+        let kw = self.next_token().clone();
+        self.expect(Tk::LeftParen, "Expected '(' after 'fn'")?;
+        let params = self.parameters()?;
         self.expect(Tk::LeftBrace, "Expected '{' before function body")?;
         let body = self.block()?;
-        Ok(Stmt::FnStmt(FnStmt::new(ident, params, body)))
+        Ok(self.lambda(&kw, params, body))
+    }
+
+    // '(params) => expression' returns the expression, '(params) => { body }'
+    // works like a function expression
+    fn arrow_lambda(&mut self) -> Result<Expr, InterpErr> {
+        let paren = self.next_token().clone();
+        let params = self.parameters()?;
+        let arrow = self.expect(Tk::Arrow, "Expected '=>' after parameters")?;
+
+        let body = if let Tk::LeftBrace = self.peek().kind {
+            //consumes the '{' token
+            self.next_token();
+            self.block()?
+        } else {
+            let value = self.expression()?;
+            Vec::from([Stmt::ReturnStmt(ReturnStmt::new(arrow, Some(value)))])
+        };
+        Ok(self.lambda(&paren, params, body))
+    }
+
+    // anonymous functions are all named 'lambda'
+    fn lambda(&self, start: &Token, params: Vec<Param>, body: Vec<Stmt>) -> Expr {
+        let mut ident = start.clone();
+        ident.kind = Tk::Identifier;
+        ident.lexeme = "lambda".to_string();
+
+        let span = start.span.to(self.previous().span);
+        Expr::Lambda(Lambda::new(FnStmt::new(ident, params, body), span))
     }
 
-    fn for_statement(&mut self) -> Result<Stmt, InterpErr> {
+    // a '(' whose matching ')' is followed by '=>'
+    fn arrow_ahead(&self) -> bool {
+        let mut depth = 0;
+        for (i, token) in self.tokens.iter().enumerate().skip(self.current) {
+            match token.kind {
+                Tk::LeftParen => depth += 1,
+                Tk::RightParen if depth == 1 => {
+                    return matches!(self.tokens.get(i + 1).map(|t| &t.kind), Some(Tk::Arrow))
+                }
+                Tk::RightParen => depth -= 1,
+                Tk::Eof => return false,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    // 'for x in iterable body', anything else is a C style loop
+    fn for_statement(&mut self, label: Option<Token>) -> Result<Stmt, InterpErr> {
+        if let (Tk::Identifier, Tk::In) = (&self.peek().kind, &self.peek_next().kind) {
+            let ident = self.next_token().clone();
+            //consumes the 'in' token
+            self.next_token();
+            let range = self.expression()?;
+            let body = Box::new(self.statement()?);
+            return Ok(Stmt::ForStmt(ForStmt::new(label, ident, range, body)));
+        }
+
         let init;
         if let Tk::Semicolon = self.peek().kind {
             init = None;
@@ -148,6 +473,7 @@ impl Parser {
         }
 
         //Condition
+        let condition_span = self.peek().span;
         let mut condition = None;
         if !matches!(self.peek().kind, Tk::Semicolon) {
             condition = Some(self.expression()?);
@@ -161,18 +487,21 @@ impl Parser {
         }
 
         //body
-        let mut body = self.statement()?;
+        let body = self.statement()?;
 
         // Desugaring into a while loop. THIS IS FUCKING MAGIC!!!
-        if let Some(inc) = increment {
-            body = Stmt::Block(Vec::from([body, Stmt::ExprStmt(ExprStmt::new(inc))]))
-        }
+        // The increment is kept apart from the body, so 'continue' still runs it
 
         if let None = condition {
-            condition = Some(Expr::Lit(Literal::Bool(true)))
+            condition = Some(Expr::Lit(Lit::new(Literal::Bool(true), condition_span)))
         }
 
-        body = Stmt::WhileStmt(WhileStmt::new(condition.unwrap(), Box::new(body)));
+        let mut body = Stmt::WhileStmt(WhileStmt::new(
+            label,
+            condition.unwrap(),
+            Box::new(body),
+            increment,
+        ));
 
         if let Some(i) = init {
             body = Stmt::Block(Vec::from([i, body]))
@@ -181,10 +510,12 @@ impl Parser {
         Ok(body)
     }
 
-    fn while_statement(&mut self) -> Result<Stmt, InterpErr> {
+    fn while_statement(&mut self, label: Option<Token>) -> Result<Stmt, InterpErr> {
         let condition = self.expression()?;
         let body = Box::new(self.statement()?);
-        Ok(Stmt::WhileStmt(WhileStmt::new(condition, body)))
+        Ok(Stmt::WhileStmt(WhileStmt::new(
+            label, condition, body, None,
+        )))
     }
 
     fn if_statement(&mut self) -> Result<Stmt, InterpErr> {
@@ -205,7 +536,9 @@ impl Parser {
         let mut stmts = Vec::new();
 
         while !matches!(self.peek().kind, Tk::RightBrace) && !self.finished() {
-            stmts.push(self.declaration()?);
+            if let Some(stmt) = self.recovering_declaration() {
+                stmts.push(stmt);
+            }
         }
 
         self.expect(Tk::RightBrace, "Expected '}' at end of block")?;
@@ -223,34 +556,152 @@ impl Parser {
     }
 
     fn assign(&mut self) -> Result<Expr, InterpErr> {
-        let expr = self.or()?;
+        let expr = self.conditional()?;
 
         if let Tk::Equal = self.peek().kind {
             //consumens the '=' token
             let equals = self.next_token().clone();
             let value = self.assign()?;
 
+            let span = expr.span().to(value.span());
+
             if let Expr::Var(v) = expr {
-                let ident = v;
-                return Ok(Expr::Assign(Assign::new(ident, Box::new(value))));
+                let ident = v.ident;
+                return Ok(Expr::Assign(Assign::new(
+                    ident,
+                    Box::new(value),
+                    None,
+                    span,
+                )));
             }
 
-            return Err(Ie::RuntimeError {
+            if let Expr::Get(g) = expr {
+                return Ok(Expr::Set(Set::new(
+                    g.object,
+                    g.ident,
+                    Box::new(value),
+                    span,
+                )));
+            }
+
+            if let Expr::Index(i) = expr {
+                let value = Box::new(value);
+                return Ok(Expr::SetIndex(SetIndex::new(
+                    i.object, i.bracket, i.index, value, span,
+                )));
+            }
+
+            return Err(Ie::SyntaxError {
                 line: equals.line,
+                span: expr.span(),
                 msg: "Invalid assignment target.".to_string(),
+                place: equals.lexeme,
             });
         }
 
+        if let Tk::PlusEqual | Tk::MinusEqual | Tk::StarEqual | Tk::SlashEqual | Tk::PercentEqual =
+            self.peek().kind
+        {
+            let operator = self.next_token().clone();
+            let value = self.assign()?;
+            let span = expr.span().to(value.span());
+            return self.update(expr, operator, value, false, span);
+        }
+
         Ok(expr)
     }
 
+    // 'x += value' and friends, and 'x++'/'x--' as an update by one. The
+    // operator token is turned into the binary operator it applies
+    fn update(
+        &self,
+        target: Expr,
+        mut operator: Token,
+        value: Expr,
+        postfix: bool,
+        span: Span,
+    ) -> Result<Expr, InterpErr> {
+        if !matches!(target, Expr::Var(_) | Expr::Get(_) | Expr::Index(_)) {
+            return Err(Ie::SyntaxError {
+                line: operator.line,
+                span: target.span(),
+                msg: "Invalid assignment target.".to_string(),
+                place: operator.lexeme,
+            });
+        }
+
+        operator.kind = match operator.kind {
+            Tk::PlusEqual | Tk::PlusPlus => Tk::Plus,
+            Tk::MinusEqual | Tk::MinusMinus => Tk::Minus,
+            Tk::StarEqual => Tk::Star,
+            Tk::SlashEqual => Tk::Slash,
+            _ => Tk::Percent,
+        };
+        let (target, value) = (Box::new(target), Box::new(value));
+        Ok(Expr::Update(Update::new(
+            target, operator, value, postfix, span,
+        )))
+    }
+
+    // the '1' of an increment or decrement
+    fn one(operator: &Token) -> Expr {
+        Expr::Lit(Lit::new(Literal::Number(Number::Int(1)), operator.span))
+    }
+
+    // 'condition ? then : else', right associative
+    fn conditional(&mut self) -> Result<Expr, InterpErr> {
+        let condition = self.coalesce()?;
+
+        if let Tk::Question = self.peek().kind {
+            //consumes the '?' token
+            self.next_token();
+            let then_branch = self.expression()?;
+            self.expect(Tk::Colon, "Expected ':' after then branch of conditional")?;
+            let else_branch = self.conditional()?;
+            let span = condition.span().to(else_branch.span());
+            return Ok(Expr::Conditional(Conditional::new(
+                Box::new(condition),
+                Box::new(then_branch),
+                Box::new(else_branch),
+                span,
+            )));
+        }
+
+        Ok(condition)
+    }
+
+    // 'a ?? b' is b only when a is null
+    fn coalesce(&mut self) -> Result<Expr, InterpErr> {
+        let mut left = self.or()?;
+
+        while let Tk::QuestionQuestion = self.peek().kind {
+            let operator = self.next_token().clone();
+            let right = self.or()?;
+            let span = left.span().to(right.span());
+            left = Expr::Logical(Logical::new(
+                Box::new(left),
+                operator,
+                Box::new(right),
+                span,
+            ))
+        }
+
+        Ok(left)
+    }
+
     fn or(&mut self) -> Result<Expr, InterpErr> {
         let mut left = self.and()?;
 
         while let Tk::Or = self.peek().kind {
             let operator = self.next_token().clone();
             let right = self.and()?;
-            left = Expr::Logical(Logical::new(Box::new(left), operator, Box::new(right)))
+            let span = left.span().to(right.span());
+            left = Expr::Logical(Logical::new(
+                Box::new(left),
+                operator,
+                Box::new(right),
+                span,
+            ))
         }
 
         Ok(left)
@@ -262,7 +713,13 @@ impl Parser {
         while let Tk::And = self.peek().kind {
             let operator = self.next_token().clone();
             let right = self.equality()?;
-            left = Expr::Logical(Logical::new(Box::new(left), operator, Box::new(right)))
+            let span = left.span().to(right.span());
+            left = Expr::Logical(Logical::new(
+                Box::new(left),
+                operator,
+                Box::new(right),
+                span,
+            ))
         }
 
         Ok(left)
@@ -275,20 +732,79 @@ impl Parser {
             let left = Box::new(expr.clone());
             let operator = self.next_token().clone();
             let right = Box::new(self.comparison()?.clone());
-            expr = Expr::Binary(Binary::new(left, operator, right));
+            let span = left.span().to(right.span());
+            expr = Expr::Binary(Binary::new(left, operator, right, span));
         }
 
         Ok(expr)
     }
 
     fn comparison(&mut self) -> Result<Expr, InterpErr> {
-        let mut expr = self.term()?;
+        let mut expr = self.bit_or()?;
 
         while let Tk::Greater | Tk::GreaterEqual | Tk::Less | Tk::LessEqual = self.peek().kind {
+            let left = Box::new(expr.clone());
+            let operator = self.next_token().clone();
+            let right = Box::new(self.bit_or()?.clone());
+            let span = left.span().to(right.span());
+            expr = Expr::Binary(Binary::new(left, operator, right, span));
+        }
+
+        Ok(expr)
+    }
+
+    // bitwise operators bind tighter than comparisons, so 'x & 1 == 0' works
+    fn bit_or(&mut self) -> Result<Expr, InterpErr> {
+        let mut expr = self.bit_xor()?;
+
+        while let Tk::Pipe = self.peek().kind {
+            let left = Box::new(expr.clone());
+            let operator = self.next_token().clone();
+            let right = Box::new(self.bit_xor()?.clone());
+            let span = left.span().to(right.span());
+            expr = Expr::Binary(Binary::new(left, operator, right, span));
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_xor(&mut self) -> Result<Expr, InterpErr> {
+        let mut expr = self.bit_and()?;
+
+        while let Tk::Caret = self.peek().kind {
+            let left = Box::new(expr.clone());
+            let operator = self.next_token().clone();
+            let right = Box::new(self.bit_and()?.clone());
+            let span = left.span().to(right.span());
+            expr = Expr::Binary(Binary::new(left, operator, right, span));
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_and(&mut self) -> Result<Expr, InterpErr> {
+        let mut expr = self.shift()?;
+
+        while let Tk::Ampersand = self.peek().kind {
+            let left = Box::new(expr.clone());
+            let operator = self.next_token().clone();
+            let right = Box::new(self.shift()?.clone());
+            let span = left.span().to(right.span());
+            expr = Expr::Binary(Binary::new(left, operator, right, span));
+        }
+
+        Ok(expr)
+    }
+
+    fn shift(&mut self) -> Result<Expr, InterpErr> {
+        let mut expr = self.term()?;
+
+        while let Tk::LessLess | Tk::GreaterGreater = self.peek().kind {
             let left = Box::new(expr.clone());
             let operator = self.next_token().clone();
             let right = Box::new(self.term()?.clone());
-            expr = Expr::Binary(Binary::new(left, operator, right));
+            let span = left.span().to(right.span());
+            expr = Expr::Binary(Binary::new(left, operator, right, span));
         }
 
         Ok(expr)
@@ -301,7 +817,8 @@ impl Parser {
             let left = Box::new(expr.clone());
             let operator = self.next_token().clone();
             let right = Box::new(self.factor()?.clone());
-            expr = Expr::Binary(Binary::new(left, operator, right));
+            let span = left.span().to(right.span());
+            expr = Expr::Binary(Binary::new(left, operator, right, span));
         }
 
         Ok(expr)
@@ -310,24 +827,72 @@ impl Parser {
     fn factor(&mut self) -> Result<Expr, InterpErr> {
         let mut expr = self.unary()?;
 
-        while let Tk::Slash | Tk::Star = self.peek().kind {
+        while let Tk::Slash | Tk::Star | Tk::Percent | Tk::SlashSlash = self.peek().kind {
             let left = Box::new(expr.clone());
             let operator = self.next_token().clone();
             let right = Box::new(self.unary()?.clone());
-            expr = Expr::Binary(Binary::new(left, operator, right));
+            let span = left.span().to(right.span());
+            expr = Expr::Binary(Binary::new(left, operator, right, span));
         }
 
         Ok(expr)
     }
 
     fn unary(&mut self) -> Result<Expr, InterpErr> {
-        while let Tk::Bang | Tk::Minus = self.peek().kind {
+        if let Tk::PlusPlus | Tk::MinusMinus = self.peek().kind {
+            let operator = self.next_token().clone();
+            let target = self.unary()?;
+            let span = operator.span.to(target.span());
+            return self.update(target, operator.clone(), Self::one(&operator), false, span);
+        }
+
+        if let Some(min) = self.min_integer() {
+            return Ok(min);
+        }
+
+        while let Tk::Bang | Tk::Minus | Tk::Tilde = self.peek().kind {
             let operator = self.next_token().clone();
             let right = Box::new(self.unary()?.clone());
-            return Ok(Expr::Unary(Unary::new(operator, right)));
+            let span = operator.span.to(right.span());
+            return Ok(Expr::Unary(Unary::new(operator, right, span)));
         }
 
-        self.call()
+        self.power()
+    }
+
+    // '-9223372036854775808': the literal alone doesn't fit in an integer (the
+    // lexer leaves it as i64::MIN), so the minus in front of it is part of it.
+    // Not when it's raised to a power, which binds tighter than the minus
+    fn min_integer(&mut self) -> Option<Expr> {
+        let (Tk::Minus, Tk::Number(Number::Int(i64::MIN))) =
+            (&self.peek().kind, &self.peek_next().kind)
+        else {
+            return None;
+        };
+        if let Some(Tk::StarStar) = self.tokens.get(self.current + 2).map(|t| &t.kind) {
+            return None;
+        }
+
+        let start = self.next_token().span;
+        let end = self.next_token().span;
+        let value = Literal::Number(Number::Int(i64::MIN));
+        Some(Expr::Lit(Lit::new(value, start.to(end))))
+    }
+
+    // right associative and tighter than a unary operator on its left:
+    // '-2 ** 2' is -4 and '2 ** 3 ** 2' is 512
+    fn power(&mut self) -> Result<Expr, InterpErr> {
+        let expr = self.call()?;
+
+        if let Tk::StarStar = self.peek().kind {
+            let left = Box::new(expr);
+            let operator = self.next_token().clone();
+            let right = Box::new(self.unary()?);
+            let span = left.span().to(right.span());
+            return Ok(Expr::Binary(Binary::new(left, operator, right, span)));
+        }
+
+        Ok(expr)
     }
 
     fn call(&mut self) -> Result<Expr, InterpErr> {
@@ -338,25 +903,62 @@ impl Parser {
                 //consumes the '(' token
                 self.next_token();
                 expr = self.finish_call(expr)?;
+            } else if let Tk::Dot = self.peek().kind {
+                //consumes the '.' token
+                self.next_token();
+                let ident = self.expect(Tk::Identifier, "Expected property name after '.'")?;
+                let span = expr.span().to(ident.span);
+                expr = Expr::Get(Get::new(Box::new(expr), ident, span));
+            } else if let Tk::LeftBracket = self.peek().kind {
+                //consumes the '[' token
+                let bracket = self.next_token().clone();
+                let index = self.expression()?;
+                let close = self.expect(Tk::RightBracket, "Expected ']' after index")?;
+                let span = expr.span().to(close.span);
+                expr = Expr::Index(Index::new(Box::new(expr), bracket, Box::new(index), span));
             } else {
                 break;
             }
         }
 
+        if let Tk::PlusPlus | Tk::MinusMinus = self.peek().kind {
+            let operator = self.next_token().clone();
+            let span = expr.span().to(operator.span);
+            return self.update(expr, operator.clone(), Self::one(&operator), true, span);
+        }
+
         Ok(expr)
     }
 
+    // positional arguments, then 'name: value' arguments
     fn finish_call(&mut self, e: Expr) -> Result<Expr, InterpErr> {
         let mut args = Vec::new();
+        let mut named = Vec::new();
         if !matches!(self.peek().kind, Tk::RightParen) {
             loop {
-                if args.len() > 255 {
-                    return Err(InterpErr::RuntimeError {
+                if args.len() + named.len() >= 255 {
+                    return Err(InterpErr::SyntaxError {
                         line: self.peek().line,
+                        span: self.peek().span,
                         msg: "functions only accept a maximum of 255 arguments".to_string(),
+                        place: self.peek().lexeme.clone(),
+                    });
+                }
+                if let (Tk::Identifier, Tk::Colon) = (&self.peek().kind, &self.peek_next().kind) {
+                    let ident = self.next_token().clone();
+                    //consumes the ':' token
+                    self.next_token();
+                    named.push((ident, self.expression()?));
+                } else if named.is_empty() {
+                    args.push(self.expression()?);
+                } else {
+                    return Err(InterpErr::SyntaxError {
+                        line: self.peek().line,
+                        span: self.peek().span,
+                        msg: "Positional argument can't follow a named argument".to_string(),
+                        place: self.peek().lexeme.clone(),
                     });
                 }
-                args.push(self.expression()?);
                 if let Tk::Comma = self.peek().kind {
                     self.next_token();
                 } else {
@@ -366,46 +968,157 @@ impl Parser {
         }
 
         let paren = self.expect(Tk::RightParen, "Expect ')' after arguments")?;
-        Ok(Expr::Call(Call::new(Box::new(e), paren, args)))
+        let span = e.span().to(paren.span);
+        Ok(Expr::Call(Call::new(Box::new(e), paren, args, named, span)))
+    }
+
+    // '"text ${expr} text"': the pieces of text around the expressions come
+    // as separate tokens, see 'Lexer::string_body'
+    fn interpolation(&mut self) -> Result<Expr, InterpErr> {
+        let start = self.peek().span;
+        let mut parts = Vec::new();
+
+        loop {
+            let piece = self.next_token().clone();
+            let (Tk::Interpolated(text) | Tk::InterpolatedEnd(text)) = &piece.kind else {
+                unreachable!("the lexer always continues an interpolated string")
+            };
+            if !text.is_empty() {
+                let text = Literal::Str(text.clone());
+                parts.push(Expr::Lit(Lit::new(text, piece.span)));
+            }
+            if let Tk::InterpolatedEnd(_) = piece.kind {
+                break;
+            }
+
+            parts.push(self.expression()?);
+            if !matches!(
+                self.peek().kind,
+                Tk::Interpolated(_) | Tk::InterpolatedEnd(_)
+            ) {
+                return Err(InterpErr::SyntaxError {
+                    line: self.peek().line,
+                    span: self.peek().span,
+                    msg: "Expected '}' after interpolated expression".to_string(),
+                    place: self.peek().lexeme.clone(),
+                });
+            }
+        }
+
+        let span = start.to(self.previous().span);
+        Ok(Expr::Interpolation(Interpolation::new(parts, span)))
     }
 
     fn primary(&mut self) -> Result<Expr, InterpErr> {
+        let span = self.peek().span;
         match self.peek().clone().kind {
             TokenKind::False => {
                 self.next_token();
-                Ok(Expr::Lit(Literal::Bool(false)))
+                Ok(Expr::Lit(Lit::new(Literal::Bool(false), span)))
             }
             TokenKind::True => {
                 self.next_token();
-                Ok(Expr::Lit(Literal::Bool(true)))
+                Ok(Expr::Lit(Lit::new(Literal::Bool(true), span)))
             }
             TokenKind::Null => {
                 self.next_token();
-                Ok(Expr::Lit(Literal::Null))
+                Ok(Expr::Lit(Lit::new(Literal::Null, span)))
             }
+            TokenKind::Number(Number::Int(i64::MIN)) => Err(Ie::SyntaxError {
+                line: self.peek().line,
+                span,
+                msg: "Number literal is too large".to_string(),
+                place: self.peek().lexeme.clone(),
+            }),
             TokenKind::Number(n) => {
                 self.next_token();
-                Ok(Expr::Lit(Literal::Number(n)))
+                Ok(Expr::Lit(Lit::new(Literal::Number(n), span)))
             }
             TokenKind::String(s) => {
                 self.next_token();
-                Ok(Expr::Lit(Literal::Str(s)))
+                Ok(Expr::Lit(Lit::new(Literal::Str(s), span)))
             }
+            TokenKind::Interpolated(_) => self.interpolation(),
+            TokenKind::LeftParen if self.arrow_ahead() => self.arrow_lambda(),
+            TokenKind::Fn => self.function_expr(),
             TokenKind::LeftParen => {
                 self.next_token();
                 let expr = Box::new(self.expression()?);
-                self.expect(Tk::RightParen, "Expected ')' after expression")?;
-                Ok(Expr::Grouping(expr))
+                let paren = self.expect(Tk::RightParen, "Expected ')' after expression")?;
+                Ok(Expr::Grouping(Grouping::new(expr, span.to(paren.span))))
+            }
+            TokenKind::LeftBracket => {
+                self.next_token();
+                let elements = self.list_elements()?;
+                let bracket = self.expect(Tk::RightBracket, "Expected ']' after list elements")?;
+                Ok(Expr::List(ListExpr::new(elements, span.to(bracket.span))))
+            }
+            // blocks are statements, so a brace where an expression is expected
+            // can only start a map
+            TokenKind::LeftBrace => {
+                let brace = self.next_token().clone();
+                let entries = self.map_entries()?;
+                let close = self.expect(Tk::RightBrace, "Expected '}' after map entries")?;
+                Ok(Expr::Map(MapExpr::new(brace, entries, span.to(close.span))))
+            }
+            TokenKind::Identifier => {
+                let ident = self.next_token().clone();
+                Ok(Expr::Var(Variable::new(ident, None, span)))
+            }
+            TokenKind::This => {
+                let keyword = self.next_token().clone();
+                Ok(Expr::This(Variable::new(keyword, None, span)))
+            }
+            TokenKind::Super => {
+                let keyword = self.next_token().clone();
+                self.expect(Tk::Dot, "Expected '.' after 'super'")?;
+                let method = self.expect(Tk::Identifier, "Expected superclass method name")?;
+                let span = span.to(method.span);
+                Ok(Expr::SuperExpr(SuperExpr::new(keyword, method, None, span)))
             }
-            TokenKind::Identifier => Ok(Expr::Var(self.next_token().clone())),
             _ => Err(Ie::SyntaxError {
                 line: self.peek().line,
+                span,
                 msg: "Expected Expression".to_string(),
                 place: self.peek().lexeme.clone(),
             }),
         }
     }
 
+    // a trailing comma is allowed after the last element
+    fn list_elements(&mut self) -> Result<Vec<Expr>, InterpErr> {
+        let mut elements = Vec::new();
+
+        while !matches!(self.peek().kind, Tk::RightBracket) && !self.finished() {
+            elements.push(self.expression()?);
+            if let Tk::Comma = self.peek().kind {
+                self.next_token();
+            } else {
+                break;
+            }
+        }
+
+        Ok(elements)
+    }
+
+    // entries are 'key: value' pairs. A trailing comma is allowed
+    fn map_entries(&mut self) -> Result<Vec<(Expr, Expr)>, InterpErr> {
+        let mut entries = Vec::new();
+
+        while !matches!(self.peek().kind, Tk::RightBrace) && !self.finished() {
+            let key = self.expression()?;
+            self.expect(Tk::Colon, "Expected ':' after map key")?;
+            entries.push((key, self.expression()?));
+            if let Tk::Comma = self.peek().kind {
+                self.next_token();
+            } else {
+                break;
+            }
+        }
+
+        Ok(entries)
+    }
+
     fn expect(&mut self, kind: TokenKind, msg: &str) -> Result<Token, InterpErr> {
         if kind == self.peek().kind {
             return Ok(self.next_token().clone());
@@ -413,6 +1126,7 @@ impl Parser {
 
         Err(Ie::SyntaxError {
             line: self.peek().line,
+            span: self.peek().span,
             msg: msg.to_string(),
             place: self.peek().lexeme.clone(),
         })
@@ -422,6 +1136,11 @@ impl Parser {
         &self.tokens[self.current]
     }
 
+    fn peek_next(&self) -> &Token {
+        let next = (self.current + 1).min(self.tokens.len() - 1);
+        &self.tokens[next]
+    }
+
     fn previous(&self) -> &Token {
         &self.tokens[self.current - 1]
     }
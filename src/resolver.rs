@@ -0,0 +1,424 @@
+use crate::{
+    ast::{ClassStmt, Expr, FnStmt, Local, Stmt},
+    error::InterpErr,
+    error::InterpErr as Ie,
+    token::Token,
+};
+use format as fmt;
+use std::collections::HashMap;
+
+// MENTAL MODEL:
+// walks the whole tree once before it is executed, simulating the scopes the
+// interpreter will create at runtime. Every local variable gets a slot in its
+// scope, in declaration order, and every use of a variable is annotated with
+// how many scopes up it was declared (depth) and its slot there. Anything not
+// found in a local scope is left unresolved and treated as a global.
+
+#[derive(Clone, Copy, PartialEq)]
+enum FnKind {
+    None,
+    Function,
+    Method,
+    Initializer,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ClassKind {
+    None,
+    Class,
+    Subclass,
+}
+
+struct Binding {
+    slot: usize,
+    defined: bool,
+}
+
+pub struct Resolver {
+    scopes: Vec<HashMap<String, Binding>>,
+    current_fn: FnKind,
+    current_class: ClassKind,
+    // labels of the loops around the current statement, innermost last
+    loops: Vec<Option<String>>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            scopes: Vec::new(),
+            current_fn: FnKind::None,
+            current_class: ClassKind::None,
+            loops: Vec::new(),
+        }
+    }
+
+    pub fn resolve(&mut self, stmts: &mut [Stmt]) -> Result<(), InterpErr> {
+        stmts
+            .iter_mut()
+            .try_for_each(|stmt| self.resolve_stmt(stmt))
+    }
+
+    fn resolve_stmt(&mut self, s: &mut Stmt) -> Result<(), InterpErr> {
+        match s {
+            Stmt::ExprStmt(expr_stmt) => self.resolve_expr(&mut expr_stmt.expr),
+            Stmt::LetStmt(let_stmt) => {
+                self.declare(&let_stmt.ident)?;
+                if let Some(init) = &mut let_stmt.initializer {
+                    self.resolve_expr(init)?;
+                }
+                self.define(&let_stmt.ident);
+                Ok(())
+            }
+            Stmt::Block(block) => {
+                self.begin_scope();
+                let result = self.resolve(block);
+                self.end_scope();
+                result
+            }
+            Stmt::IfStmt(if_stmt) => {
+                self.resolve_expr(&mut if_stmt.condition)?;
+                self.resolve_stmt(&mut if_stmt.if_branch)?;
+                match &mut if_stmt.else_branch {
+                    Some(branch) => self.resolve_stmt(branch),
+                    None => Ok(()),
+                }
+            }
+            Stmt::WhileStmt(while_stmt) => {
+                self.resolve_expr(&mut while_stmt.condition)?;
+                self.resolve_loop(&while_stmt.label, &mut while_stmt.body)?;
+                match &mut while_stmt.increment {
+                    Some(increment) => self.resolve_expr(increment),
+                    None => Ok(()),
+                }
+            }
+            // the loop variable gets a scope of its own, created anew on every iteration
+            Stmt::ForStmt(for_stmt) => {
+                self.resolve_expr(&mut for_stmt.range)?;
+                self.begin_scope();
+                self.declare(&for_stmt.ident)?;
+                self.define(&for_stmt.ident);
+                let result = self.resolve_loop(&for_stmt.label, &mut for_stmt.body);
+                self.end_scope();
+                result
+            }
+            Stmt::FnStmt(fn_stmt) => {
+                // declared and defined at once, so the function can refer to itself
+                self.declare(&fn_stmt.ident)?;
+                self.define(&fn_stmt.ident);
+                self.resolve_function(fn_stmt, FnKind::Function)
+            }
+            Stmt::ReturnStmt(return_stmt) => {
+                if let FnKind::None = self.current_fn {
+                    return static_error(&return_stmt.keywowrd, "Can't return from top-level code");
+                }
+
+                match &mut return_stmt.expr {
+                    Some(_) if self.current_fn == FnKind::Initializer => static_error(
+                        &return_stmt.keywowrd,
+                        "Can't return a value from an initializer",
+                    ),
+                    Some(expr) => self.resolve_expr(expr),
+                    None => Ok(()),
+                }
+            }
+            Stmt::BreakStmt(break_stmt) => {
+                self.resolve_jump(&break_stmt.keyword, &break_stmt.label)
+            }
+            Stmt::ContinueStmt(continue_stmt) => {
+                self.resolve_jump(&continue_stmt.keyword, &continue_stmt.label)
+            }
+            // the caught value gets a scope of its own, around the catch block
+            Stmt::TryStmt(try_stmt) => {
+                self.resolve_stmt(&mut try_stmt.body)?;
+                if let Some((ident, body)) = &mut try_stmt.catch_clause {
+                    self.begin_scope();
+                    self.declare(ident)?;
+                    self.define(ident);
+                    let result = self.resolve_stmt(body);
+                    self.end_scope();
+                    result?;
+                }
+                match &mut try_stmt.finally_body {
+                    Some(body) => self.resolve_stmt(body),
+                    None => Ok(()),
+                }
+            }
+            Stmt::ThrowStmt(throw_stmt) => self.resolve_expr(&mut throw_stmt.value),
+            Stmt::ClassStmt(class_stmt) => self.resolve_class(class_stmt),
+        }
+    }
+
+    fn resolve_loop(&mut self, label: &Option<Token>, body: &mut Stmt) -> Result<(), InterpErr> {
+        self.loops
+            .push(label.as_ref().map(|label| label.lexeme.clone()));
+        let result = self.resolve_stmt(body);
+        self.loops.pop();
+        result
+    }
+
+    // 'break' and 'continue' must be inside a loop, of the function they belong
+    // to, with the label they name
+    fn resolve_jump(&mut self, keyword: &Token, label: &Option<Token>) -> Result<(), InterpErr> {
+        match label {
+            _ if self.loops.is_empty() => static_error(
+                keyword,
+                &fmt!("Can't use '{}' outside of a loop", keyword.lexeme),
+            ),
+            Some(label) if !self.loops.contains(&Some(label.lexeme.clone())) => {
+                static_error(label, &fmt!("No loop labelled '{}'", label.lexeme))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn resolve_class(&mut self, c: &mut ClassStmt) -> Result<(), InterpErr> {
+        let enclosing = self.current_class;
+        self.current_class = ClassKind::Class;
+
+        self.declare(&c.ident)?;
+        self.define(&c.ident);
+
+        // mirrors the environments created by the interpreter: one binding 'super'
+        // (subclasses only) and one binding 'this' for every bound method
+        if let Some(superclass) = &mut c.superclass {
+            self.current_class = ClassKind::Subclass;
+            self.resolve_expr(superclass)?;
+            self.begin_scope();
+            self.define_name("super");
+        }
+
+        self.begin_scope();
+        self.define_name("this");
+
+        let result = c.methods.iter_mut().try_for_each(|method| {
+            let kind = if method.ident.lexeme == "init" {
+                FnKind::Initializer
+            } else {
+                FnKind::Method
+            };
+            self.resolve_function(method, kind)
+        });
+
+        self.end_scope();
+        if c.superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.current_class = enclosing;
+        result
+    }
+
+    // parameters and the top level of the body share a single scope, just like
+    // the environment created by 'LoxFunction::call'
+    fn resolve_function(&mut self, f: &mut FnStmt, kind: FnKind) -> Result<(), InterpErr> {
+        let enclosing = self.current_fn;
+        self.current_fn = kind;
+        let loops = std::mem::take(&mut self.loops);
+        self.begin_scope();
+
+        //defaults see the parameters before them
+        let result = f
+            .params
+            .iter_mut()
+            .try_for_each(|param| {
+                self.declare(&param.ident)?;
+                if let Some(default) = &mut param.default {
+                    self.resolve_expr(default)?;
+                }
+                self.define(&param.ident);
+                Ok(())
+            })
+            .and_then(|_| self.resolve(&mut f.body));
+
+        self.end_scope();
+        self.loops = loops;
+        self.current_fn = enclosing;
+        result
+    }
+
+    fn resolve_expr(&mut self, e: &mut Expr) -> Result<(), InterpErr> {
+        match e {
+            Expr::Assign(assign) => {
+                self.resolve_expr(&mut assign.value)?;
+                assign.local = self.resolve_local(&assign.ident);
+                Ok(())
+            }
+            Expr::Unary(unary) => self.resolve_expr(&mut unary.right),
+            Expr::Binary(binary) => {
+                self.resolve_expr(&mut binary.left)?;
+                self.resolve_expr(&mut binary.right)
+            }
+            Expr::Call(call) => {
+                self.resolve_expr(&mut call.callee)?;
+                call.args
+                    .iter_mut()
+                    .chain(call.named.iter_mut().map(|(_, arg)| arg))
+                    .try_for_each(|arg| self.resolve_expr(arg))
+            }
+            Expr::Grouping(grouping) => self.resolve_expr(&mut grouping.expr),
+            Expr::Lit(_) => Ok(()),
+            Expr::Logical(logical) => {
+                self.resolve_expr(&mut logical.left)?;
+                self.resolve_expr(&mut logical.right)
+            }
+            Expr::Var(var) => {
+                if let Some(scope) = self.scopes.last() {
+                    if let Some(Binding { defined: false, .. }) = scope.get(&var.ident.lexeme) {
+                        return static_error(
+                            &var.ident,
+                            "Can't read local variable in its own initializer",
+                        );
+                    }
+                }
+
+                var.local = self.resolve_local(&var.ident);
+                Ok(())
+            }
+            Expr::List(list) => list
+                .elements
+                .iter_mut()
+                .try_for_each(|element| self.resolve_expr(element)),
+            Expr::Map(map) => map.entries.iter_mut().try_for_each(|(key, value)| {
+                self.resolve_expr(key)?;
+                self.resolve_expr(value)
+            }),
+            Expr::Lambda(lambda) => self.resolve_function(&mut lambda.function, FnKind::Function),
+            Expr::Conditional(conditional) => {
+                self.resolve_expr(&mut conditional.condition)?;
+                self.resolve_expr(&mut conditional.then_branch)?;
+                self.resolve_expr(&mut conditional.else_branch)
+            }
+            Expr::Interpolation(interpolation) => interpolation
+                .parts
+                .iter_mut()
+                .try_for_each(|part| self.resolve_expr(part)),
+            Expr::Update(update) => {
+                self.resolve_expr(&mut update.target)?;
+                self.resolve_expr(&mut update.value)
+            }
+            Expr::Index(index) => {
+                self.resolve_expr(&mut index.object)?;
+                self.resolve_expr(&mut index.index)
+            }
+            Expr::SetIndex(set_index) => {
+                self.resolve_expr(&mut set_index.value)?;
+                self.resolve_expr(&mut set_index.object)?;
+                self.resolve_expr(&mut set_index.index)
+            }
+            Expr::Get(get) => self.resolve_expr(&mut get.object),
+            Expr::Set(set) => {
+                self.resolve_expr(&mut set.value)?;
+                self.resolve_expr(&mut set.object)
+            }
+            Expr::This(this) => {
+                if let ClassKind::None = self.current_class {
+                    return static_error(&this.ident, "Can't use 'this' outside of a class");
+                }
+
+                this.local = self.resolve_local(&this.ident);
+                Ok(())
+            }
+            Expr::SuperExpr(super_expr) => {
+                match self.current_class {
+                    ClassKind::None => {
+                        return static_error(
+                            &super_expr.keyword,
+                            "Can't use 'super' outside of a class",
+                        )
+                    }
+                    ClassKind::Class => {
+                        return static_error(
+                            &super_expr.keyword,
+                            "Can't use 'super' in a class with no superclass",
+                        )
+                    }
+                    ClassKind::Subclass => (),
+                }
+
+                super_expr.local = self.resolve_local(&super_expr.keyword);
+                Ok(())
+            }
+        }
+    }
+
+    fn resolve_local(&self, ident: &Token) -> Option<Local> {
+        self.scopes
+            .iter()
+            .rev()
+            .enumerate()
+            .find_map(|(depth, scope)| {
+                scope.get(&ident.lexeme).map(|binding| Local {
+                    depth,
+                    slot: binding.slot,
+                })
+            })
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    // reserves the next slot of the current scope for the name, but marks it
+    // as not ready to be read yet
+    fn declare(&mut self, ident: &Token) -> Result<(), InterpErr> {
+        let Some(scope) = self.scopes.last_mut() else {
+            return Ok(());
+        };
+
+        if scope.contains_key(&ident.lexeme) {
+            return static_error(ident, "Already a variable with this name in this scope");
+        }
+
+        let slot = scope.len();
+        scope.insert(
+            ident.lexeme.clone(),
+            Binding {
+                slot,
+                defined: false,
+            },
+        );
+        Ok(())
+    }
+
+    fn define(&mut self, ident: &Token) {
+        if let Some(binding) = self
+            .scopes
+            .last_mut()
+            .and_then(|scope| scope.get_mut(&ident.lexeme))
+        {
+            binding.defined = true;
+        }
+    }
+
+    fn define_name(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            let slot = scope.len();
+            scope.insert(
+                name.to_string(),
+                Binding {
+                    slot,
+                    defined: true,
+                },
+            );
+        }
+    }
+}
+
+fn static_error(ident: &Token, msg: &str) -> Result<(), InterpErr> {
+    Err(Ie::SyntaxError {
+        line: ident.line,
+        span: ident.span,
+        msg: msg.to_string(),
+        place: ident.lexeme.clone(),
+    })
+}
@@ -0,0 +1,133 @@
+use crate::ast::FnStmt;
+use format as fmt;
+
+// MENTAL MODEL:
+// a signature describes the parameters of anything callable, the same way for
+// both backends: their names, how many of the leading ones must be passed (the
+// rest have a default), and whether a rest parameter collects the extra
+// positional arguments. Binding matches the arguments of a call to the
+// parameters: positional ones fill the parameters in order, named ones fill
+// the parameter with that name. Filling in defaults is left to the callee,
+// since they are evaluated inside of it. Natives can also have parameters
+// after the rest parameter, which can only be passed by name.
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Signature {
+    pub params: Vec<String>, //without the rest parameter
+    pub required: usize,
+    pub positional: usize, //the parameters before the rest parameter
+    pub rest: bool,
+}
+
+// the arguments of a call matched to the parameters: one slot per parameter,
+// empty if it was left to its default, and what's left for the rest parameter
+pub struct Bound<V> {
+    pub slots: Vec<Option<V>>,
+    pub rest: Vec<V>,
+}
+
+impl<V> Bound<V> {
+    // the values in parameter order, 'missing' standing in for the ones left out
+    pub fn into_values(self, missing: V) -> Vec<V>
+    where
+        V: Clone,
+    {
+        let slots = self.slots.into_iter();
+        slots
+            .map(|slot| slot.unwrap_or_else(|| missing.clone()))
+            .chain(self.rest)
+            .collect()
+    }
+}
+
+impl Signature {
+    pub fn new(params: Vec<String>, required: usize, rest: bool) -> Self {
+        Self {
+            positional: params.len(),
+            params,
+            required,
+            rest,
+        }
+    }
+
+    pub fn of(f: &FnStmt) -> Self {
+        let params: Vec<String> = f
+            .params
+            .iter()
+            .filter(|param| !param.rest)
+            .map(|param| param.ident.lexeme.clone())
+            .collect();
+        let required = f
+            .params
+            .iter()
+            .filter(|param| param.default.is_none() && !param.rest);
+        let rest = f.params.last().is_some_and(|param| param.rest);
+        Self::new(params, required.count(), rest)
+    }
+
+    // every parameter must be passed
+    pub fn fixed(params: &[&str]) -> Self {
+        let params: Vec<String> = params.iter().map(|param| param.to_string()).collect();
+        Self::new(params.clone(), params.len(), false)
+    }
+
+    // the parameters, then a rest parameter followed by optional parameters
+    // only passed by name
+    pub fn variadic(params: &[&str], named: &[&str]) -> Self {
+        let mut signature = Self::fixed(params);
+        signature
+            .params
+            .extend(named.iter().map(|param| param.to_string()));
+        signature.rest = true;
+        signature
+    }
+
+    // 'Some(n)' if exactly n positional arguments bind without any work
+    pub fn exact(&self) -> Option<usize> {
+        (!self.rest && self.required == self.params.len()).then_some(self.params.len())
+    }
+
+    pub fn bind<V>(&self, positional: Vec<V>, named: Vec<(String, V)>) -> Result<Bound<V>, String> {
+        let argc = positional.len();
+        if argc > self.positional && !self.rest {
+            return Err(self.arity_error(argc));
+        }
+
+        let mut slots: Vec<Option<V>> = Vec::with_capacity(self.params.len());
+        let mut positional = positional.into_iter();
+        slots.extend(positional.by_ref().take(self.positional).map(Some));
+        slots.resize_with(self.params.len(), || None);
+        let rest = positional.collect();
+
+        let by_name = !named.is_empty();
+        for (name, value) in named {
+            let Some(i) = self.params.iter().position(|param| *param == name) else {
+                return Err(fmt!("Unexpected argument '{name}'"));
+            };
+            if slots[i].is_some() {
+                return Err(fmt!("Argument '{name}' passed twice"));
+            }
+            slots[i] = Some(value);
+        }
+
+        if let Some(i) = slots[..self.required].iter().position(Option::is_none) {
+            if !by_name {
+                return Err(self.arity_error(argc));
+            }
+            return Err(fmt!("Missing argument '{}'", self.params[i]));
+        }
+
+        Ok(Bound { slots, rest })
+    }
+
+    fn arity_error(&self, argc: usize) -> String {
+        let expected = if self.rest {
+            fmt!("at least {}", self.required)
+        } else if self.required == self.positional {
+            fmt!("{}", self.required)
+        } else {
+            fmt!("{} to {}", self.required, self.positional)
+        };
+        fmt!("Expected {expected} arguments, but {argc} where provided")
+    }
+}
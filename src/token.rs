@@ -1,14 +1,42 @@
+use crate::number::Number;
+
 // Token = lexeme + some information
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub kind: TokenKind, //type of the token
     pub lexeme: String,  //substring representation of the token
     pub line: usize,     //token line in the source
+    pub column: usize,   //column of the first character of the token, starting at 1
+    pub span: Span,      //byte range of the token in the source
 }
 
 impl Token {
-    pub fn new(kind: TokenKind, lexeme: String, line: usize) -> Self {
-        Self { kind, lexeme, line }
+    pub fn new(kind: TokenKind, lexeme: String, line: usize, column: usize, span: Span) -> Self {
+        Self {
+            kind,
+            lexeme,
+            line,
+            column,
+            span,
+        }
+    }
+}
+
+// Byte range [start, end) of some piece of the source code
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    // smallest span covering both spans
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
     }
 }
 
@@ -19,15 +47,26 @@ pub enum TokenKind {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
+    Colon,
     Dot,
+    Question,
     Minus,
+    Percent,
     Plus,
     Semicolon,
     Slash,
     Star,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
 
     //single or double char tokens
+    Arrow,
+    Ellipsis,
     Bang,
     BangEqual,
     Equal,
@@ -36,26 +75,47 @@ pub enum TokenKind {
     GreaterEqual,
     Less,
     LessEqual,
+    GreaterGreater,
+    LessLess,
+    SlashSlash,
+    StarStar,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PercentEqual,
+    PlusPlus,
+    MinusMinus,
+    QuestionQuestion,
 
     // literals
     Identifier,
     String(String),
-    Number(f64),
+    Interpolated(String),    //the text of a string before an embedded expression
+    InterpolatedEnd(String), //the text after the last embedded expression
+    Number(Number),
 
     //keywords
     And,
+    Break,
+    Catch,
     Class,
+    Continue,
     Else,
     False,
+    Finally,
     Fn,
     For,
     If,
+    In,
     Null,
     Or,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Let,
     While,
 
@@ -0,0 +1,1244 @@
+use crate::{
+    chunk::{Constant, Function, OpCode},
+    error::{CaughtError, InterpErr, ThrownValue, TraceFrame},
+    loxstd::{
+        insert_index, join_line, list_index, slice_bounds, Clock, Eprint, Print, Range, SlotFn,
+        LIST_METHODS, MAP_METHODS,
+    },
+    map::{MapKey, OrderedMap, KEY_ERROR},
+    number::{
+        add, divide, floor_divide, integer_op, modulo, multiply, power, shift_left, shift_right,
+        subtract, ArithmeticFn, IntegerFn, Number,
+    },
+    obj::Callable,
+    signature::Signature,
+    token::Span,
+};
+use format as fmt;
+use std::{cell::RefCell, collections::HashMap, fmt::Display, rc::Rc};
+
+// MENTAL MODEL:
+// executes the bytecode produced by the compiler. Every value an expression
+// produces is pushed on a single operand stack, and every function call gets a
+// frame pointing at the start of its window in that stack: slot 0 is the
+// callee (or the receiver for methods), followed by the arguments and locals.
+// Closures capture variables through upvalues, which point into the stack
+// while the variable is alive and own the value after it goes out of scope.
+// An error raised inside a try block unwinds the frames and the stack back to
+// where the block started, and resumes at its handler.
+
+// the deepest calls can nest, the tree walker stops at the same depth
+pub const FRAMES_MAX: usize = 4096;
+
+pub type NativeFn = fn(&[Value]) -> Result<Value, String>;
+
+#[derive(Clone)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(Number),
+    Str(Rc<str>),
+    Closure(Rc<Closure>),
+    Native(Rc<Native>),
+    Class(Rc<Class>),
+    Instance(Rc<Instance>),
+    BoundMethod(Rc<BoundMethod>),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<OrderedMap<Value>>>),
+    Range(i64, i64),
+    NativeMethod(Rc<NativeMethod>),
+    Error(Rc<CaughtError>),
+    Exception(Rc<Exception>), //only ever held by the hidden locals of a try statement
+}
+
+pub struct Native {
+    name: String,
+    signature: Signature,
+    function: NativeFn,
+}
+
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+pub struct Closure {
+    function: Rc<Function>,
+    upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+pub struct Class {
+    name: String,
+    methods: RefCell<HashMap<Rc<str>, Rc<Closure>>>,
+}
+
+pub struct Instance {
+    class: Rc<Class>,
+    fields: RefCell<HashMap<Rc<str>, Value>>,
+}
+
+pub struct BoundMethod {
+    receiver: Value,
+    method: Rc<Closure>,
+}
+
+// a method of a list or map, see 'LIST_METHODS' and 'MAP_METHODS'
+pub struct NativeMethod {
+    receiver: Value,
+    name: &'static str,
+    signature: Signature,
+    at: (usize, Span), //the name it was accessed with, its errors point there
+}
+
+// what a handler receives: the value for the catch block, and the error to raise
+// again if there is only a finally block
+pub struct Exception {
+    value: Value,
+    error: InterpErr,
+}
+
+// where to resume when an error is raised inside a try block, and how many
+// frames and stack slots were in use when the block started
+struct Handler {
+    ip: usize,
+    frames: usize,
+    stack: usize,
+}
+
+struct CallFrame {
+    closure: Rc<Closure>,
+    ip: usize,
+    base: usize,
+    missing: Vec<bool>, //parameters left to their default, empty when all were passed
+}
+
+impl CallFrame {
+    fn read_byte(&mut self) -> u8 {
+        let byte = self.closure.function.chunk.code[self.ip];
+        self.ip += 1;
+        byte
+    }
+
+    fn read_short(&mut self) -> u16 {
+        let high = self.read_byte();
+        let low = self.read_byte();
+        u16::from_be_bytes([high, low])
+    }
+
+    fn read_constant(&mut self) -> &Constant {
+        let index = self.read_short() as usize;
+        &self.closure.function.chunk.constants[index]
+    }
+
+    fn read_string(&mut self) -> Rc<str> {
+        match self.read_constant() {
+            Constant::Str(s) => Rc::clone(s),
+            _ => unreachable!("name operands always refer to string constants"),
+        }
+    }
+
+    fn position(&self) -> (usize, Span) {
+        self.position_at(self.ip.saturating_sub(1))
+    }
+
+    fn position_at(&self, offset: usize) -> (usize, Span) {
+        self.closure.function.chunk.position(offset)
+    }
+}
+
+pub struct Vm {
+    stack: Vec<Value>,
+    frames: Vec<CallFrame>,
+    globals: HashMap<Rc<str>, Value>,
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    handlers: Vec<Handler>,
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        let mut vm = Self {
+            stack: Vec::new(),
+            frames: Vec::new(),
+            globals: HashMap::new(),
+            open_upvalues: Vec::new(),
+            handlers: Vec::new(),
+        };
+
+        vm.define_native("clock", &Clock {}, clock);
+        vm.define_native("print", &Print {}, print);
+        vm.define_native("eprint", &Eprint {}, eprint);
+        vm.define_native("range", &Range {}, range);
+        vm
+    }
+
+    // natives share their name and signature with the ones of the tree walker
+    fn define_native(&mut self, name: &str, callable: &dyn Callable, function: NativeFn) {
+        let native = Native {
+            name: callable.to_string(),
+            signature: callable.signature(),
+            function,
+        };
+        self.globals
+            .insert(Rc::from(name), Value::Native(Rc::new(native)));
+    }
+
+    pub fn interpret(&mut self, function: Rc<Function>) -> Result<(), InterpErr> {
+        let closure = Rc::new(Closure {
+            function,
+            upvalues: Vec::new(),
+        });
+        self.stack.push(Value::Closure(Rc::clone(&closure)));
+
+        let mut frame = CallFrame {
+            closure,
+            ip: 0,
+            base: 0,
+            missing: Vec::new(),
+        };
+
+        let result = loop {
+            match self.run(&mut frame) {
+                Err(e) if !self.handlers.is_empty() => self.catch(&mut frame, e),
+                result => break result.map_err(|e| self.traced(e, &frame)),
+            }
+        };
+
+        if result.is_err() {
+            self.stack.clear();
+            self.frames.clear();
+            self.open_upvalues.clear();
+            self.handlers.clear();
+        }
+        result
+    }
+
+    // unwinds to the innermost handler, which finds the exception on top of the stack
+    fn catch(&mut self, frame: &mut CallFrame, e: InterpErr) {
+        let handler = self.handlers.pop().unwrap();
+
+        // the stack the error was raised in is only known before unwinding
+        let error = self.traced(e, frame);
+        let value = match &error {
+            InterpErr::Thrown {
+                value: ThrownValue::Value(value),
+                ..
+            } => value.clone(),
+            e => match e.clone().caught() {
+                Ok(caught) => Value::Error(Rc::new(caught)),
+                Err(_) => unreachable!("only runtime errors are raised while running"),
+            },
+        };
+
+        while self.frames.len() > handler.frames {
+            *frame = self.frames.pop().unwrap();
+        }
+        self.close_upvalues(handler.stack);
+        self.stack.truncate(handler.stack);
+
+        self.push(Value::Exception(Rc::new(Exception { value, error })));
+        frame.ip = handler.ip;
+    }
+
+    // the frame being executed is kept out of 'frames', which only holds the callers
+    fn run(&mut self, frame: &mut CallFrame) -> Result<(), InterpErr> {
+        loop {
+            let byte = frame.read_byte();
+            let Some(op) = OpCode::from_byte(byte) else {
+                return Err(error(frame, &fmt!("Unknown opcode {byte}")));
+            };
+
+            match op {
+                OpCode::Constant => {
+                    let value = match frame.read_constant() {
+                        Constant::Number(n) => Value::Number(*n),
+                        Constant::Str(s) => Value::Str(Rc::clone(s)),
+                        Constant::Function(_) => unreachable!("functions are loaded by Closure"),
+                    };
+                    self.push(value);
+                }
+                OpCode::Null => self.push(Value::Null),
+                OpCode::True => self.push(Value::Bool(true)),
+                OpCode::False => self.push(Value::Bool(false)),
+                OpCode::Pick => {
+                    let distance = frame.read_byte() as usize;
+                    let value = self.peek(distance).clone();
+                    self.push(value);
+                }
+                OpCode::Bury => {
+                    let distance = frame.read_byte() as usize;
+                    let value = self.pop();
+                    self.stack.insert(self.stack.len() - distance, value);
+                }
+                OpCode::Pop => {
+                    self.pop();
+                }
+                OpCode::GetLocal => {
+                    let slot = frame.read_byte() as usize;
+                    self.push(self.stack[frame.base + slot].clone());
+                }
+                OpCode::SetLocal => {
+                    let slot = frame.read_byte() as usize;
+                    self.stack[frame.base + slot] = self.peek(0).clone();
+                }
+                OpCode::GetGlobal => {
+                    let name = frame.read_string();
+                    match self.globals.get(&name) {
+                        Some(value) => self.push(value.clone()),
+                        None => {
+                            return Err(error(frame, &fmt!("Undefined variable '{name}'")));
+                        }
+                    }
+                }
+                OpCode::DefineGlobal => {
+                    let name = frame.read_string();
+                    let value = self.pop();
+                    self.globals.insert(name, value);
+                }
+                OpCode::SetGlobal => {
+                    let name = frame.read_string();
+                    let value = self.peek(0).clone();
+                    match self.globals.get_mut(&name) {
+                        Some(global) => *global = value,
+                        None => {
+                            return Err(error(frame, &fmt!("Undefined variable '{name}'")));
+                        }
+                    }
+                }
+                OpCode::GetUpvalue => {
+                    let index = frame.read_byte() as usize;
+                    let value = match &*RefCell::borrow(&frame.closure.upvalues[index]) {
+                        Upvalue::Open(slot) => self.stack[*slot].clone(),
+                        Upvalue::Closed(value) => value.clone(),
+                    };
+                    self.push(value);
+                }
+                OpCode::SetUpvalue => {
+                    let index = frame.read_byte() as usize;
+                    let value = self.peek(0).clone();
+                    match &mut *RefCell::borrow_mut(&frame.closure.upvalues[index]) {
+                        Upvalue::Open(slot) => self.stack[*slot] = value,
+                        Upvalue::Closed(closed) => *closed = value,
+                    }
+                }
+                // errors about the object point at it, see 'OpCode::GetProperty'
+                OpCode::GetProperty => {
+                    let object = frame.ip - 1;
+                    let name = frame.read_string();
+                    let instance = match self.peek(0).clone() {
+                        Value::Instance(instance) => instance,
+                        receiver @ (Value::List(_) | Value::Map(_)) => {
+                            let method = bind_native_method(frame.position(), receiver, &name)?;
+                            self.pop();
+                            self.push(method);
+                            continue;
+                        }
+                        Value::Error(err) => {
+                            let value = error_field(frame.position(), &err, &name)?;
+                            self.pop();
+                            self.push(value);
+                            continue;
+                        }
+                        _ => {
+                            return Err(error_at(
+                                frame.position_at(object),
+                                "Only instances have properties",
+                            ))
+                        }
+                    };
+
+                    let field = RefCell::borrow(&instance.fields).get(&name).cloned();
+                    let value = match field {
+                        Some(value) => value,
+                        None => self.bind_method(frame, &instance.class, &name)?,
+                    };
+                    self.pop();
+                    self.push(value);
+                }
+                OpCode::SetProperty => {
+                    let object = frame.ip - 1;
+                    let name = frame.read_string();
+                    let Value::Instance(instance) = self.peek(1).clone() else {
+                        return Err(error_at(
+                            frame.position_at(object),
+                            "Only instances have fields",
+                        ));
+                    };
+
+                    let value = self.pop();
+                    RefCell::borrow_mut(&instance.fields).insert(name, value.clone());
+                    self.pop();
+                    self.push(value);
+                }
+                OpCode::GetSuper => {
+                    let name = frame.read_string();
+                    let Value::Class(superclass) = self.pop() else {
+                        unreachable!("'super' always holds a class")
+                    };
+
+                    let method = self.bind_method(frame, &superclass, &name)?;
+                    self.pop();
+                    self.push(method);
+                }
+                OpCode::Equal => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.push(Value::Bool(left == right));
+                }
+                OpCode::Greater => self.number_op(frame, |l, r| Value::Bool(l > r))?,
+                OpCode::GreaterEqual => self.number_op(frame, |l, r| Value::Bool(l >= r))?,
+                OpCode::Less => self.number_op(frame, |l, r| Value::Bool(l < r))?,
+                OpCode::LessEqual => self.number_op(frame, |l, r| Value::Bool(l <= r))?,
+                OpCode::Subtract => self.arithmetic_op(frame, subtract)?,
+                OpCode::Multiply => self.arithmetic_op(frame, multiply)?,
+                OpCode::Divide => self.arithmetic_op(frame, divide)?,
+                OpCode::Modulo => self.arithmetic_op(frame, modulo)?,
+                OpCode::FloorDivide => self.arithmetic_op(frame, floor_divide)?,
+                OpCode::Power => self.arithmetic_op(frame, power)?,
+                OpCode::BitAnd => self.integer_op(frame, |l, r| Some(l & r))?,
+                OpCode::BitOr => self.integer_op(frame, |l, r| Some(l | r))?,
+                OpCode::BitXor => self.integer_op(frame, |l, r| Some(l ^ r))?,
+                OpCode::ShiftLeft => self.integer_op(frame, shift_left)?,
+                OpCode::ShiftRight => self.integer_op(frame, shift_right)?,
+                OpCode::Add => {
+                    let value = match (self.peek(1), self.peek(0)) {
+                        (Value::Number(l), Value::Number(r)) => {
+                            Value::Number(add(*l, *r).map_err(|msg| error(frame, msg))?)
+                        }
+                        (Value::Str(l), Value::Str(r)) => Value::Str(Rc::from(fmt!("{l}{r}"))),
+                        _ => {
+                            return Err(error(frame, "Operand must be 'string' or 'number'"));
+                        }
+                    };
+                    self.pop();
+                    self.pop();
+                    self.push(value);
+                }
+                OpCode::Not => {
+                    let value = self.pop();
+                    self.push(Value::Bool(!truthy(&value)));
+                }
+                OpCode::Negate => {
+                    let Value::Number(n) = self.peek(0) else {
+                        return Err(error(frame, "Operand must be a number"));
+                    };
+                    let n = n.negate().map_err(|msg| error(frame, msg))?;
+                    self.pop();
+                    self.push(Value::Number(n));
+                }
+                OpCode::BitNot => {
+                    let Value::Number(n) = self.peek(0) else {
+                        return Err(error(frame, "Operand must be a number"));
+                    };
+                    let n = n.bit_not().map_err(|msg| error(frame, msg))?;
+                    self.pop();
+                    self.push(Value::Number(n));
+                }
+                OpCode::Jump => {
+                    let offset = frame.read_short() as usize;
+                    frame.ip += offset;
+                }
+                OpCode::JumpIfFalse => {
+                    let offset = frame.read_short() as usize;
+                    if !truthy(self.peek(0)) {
+                        frame.ip += offset;
+                    }
+                }
+                OpCode::JumpIfNotNull => {
+                    let offset = frame.read_short() as usize;
+                    if !matches!(self.peek(0), Value::Null) {
+                        frame.ip += offset;
+                    }
+                }
+                OpCode::Loop => {
+                    let offset = frame.read_short() as usize;
+                    frame.ip -= offset;
+                }
+                OpCode::JumpIfPassed => {
+                    let param = frame.read_byte() as usize;
+                    let offset = frame.read_short() as usize;
+                    if !frame.missing.get(param).copied().unwrap_or(false) {
+                        frame.ip += offset;
+                    }
+                }
+                OpCode::Call => {
+                    let argc = frame.read_byte() as usize;
+                    self.call_value(frame, argc)?;
+                }
+                OpCode::CallNamed => {
+                    let argc = frame.read_byte() as usize;
+                    let count = frame.read_byte() as usize;
+                    let names: Vec<_> = (0..count).map(|_| frame.read_string()).collect();
+                    let values = self.stack.split_off(self.stack.len() - count);
+                    let named = names.iter().map(|name| name.to_string()).zip(values);
+                    self.call_named(frame, argc, named.collect())?;
+                }
+                OpCode::Invoke => {
+                    let object = frame.ip - 1;
+                    let name = frame.read_string();
+                    let property = frame.ip - 1;
+                    let argc = frame.read_byte() as usize;
+                    let instance = match self.peek(argc).clone() {
+                        Value::Instance(instance) => instance,
+                        receiver @ (Value::List(_) | Value::Map(_)) => {
+                            let callee = self.stack.len() - argc - 1;
+                            let at = frame.position_at(property);
+                            self.stack[callee] = bind_native_method(at, receiver, &name)?;
+                            self.call_value(frame, argc)?;
+                            continue;
+                        }
+                        Value::Error(err) => {
+                            let callee = self.stack.len() - argc - 1;
+                            let at = frame.position_at(property);
+                            self.stack[callee] = error_field(at, &err, &name)?;
+                            self.call_value(frame, argc)?;
+                            continue;
+                        }
+                        _ => {
+                            return Err(error_at(
+                                frame.position_at(object),
+                                "Only instances have properties",
+                            ))
+                        }
+                    };
+
+                    // a field holding a function shadows the method
+                    let field = RefCell::borrow(&instance.fields).get(&name).cloned();
+                    match field {
+                        Some(value) => {
+                            let callee = self.stack.len() - argc - 1;
+                            self.stack[callee] = value;
+                            self.call_value(frame, argc)?;
+                        }
+                        None => {
+                            let class = &instance.class;
+                            self.invoke_from_class(frame, class, &name, property, argc)?
+                        }
+                    }
+                }
+                OpCode::SuperInvoke => {
+                    let name = frame.read_string();
+                    let property = frame.ip - 1;
+                    let argc = frame.read_byte() as usize;
+                    let Value::Class(superclass) = self.pop() else {
+                        unreachable!("'super' always holds a class")
+                    };
+                    self.invoke_from_class(frame, &superclass, &name, property, argc)?;
+                }
+                OpCode::Closure => {
+                    let Constant::Function(function) = frame.read_constant() else {
+                        unreachable!("closures are always created from function constants")
+                    };
+                    let function = Rc::clone(function);
+
+                    let mut upvalues = Vec::with_capacity(function.upvalue_count);
+                    for _ in 0..function.upvalue_count {
+                        let is_local = frame.read_byte() == 1;
+                        let index = frame.read_byte() as usize;
+                        if is_local {
+                            upvalues.push(self.capture_upvalue(frame.base + index));
+                        } else {
+                            upvalues.push(Rc::clone(&frame.closure.upvalues[index]));
+                        }
+                    }
+
+                    self.push(Value::Closure(Rc::new(Closure { function, upvalues })));
+                }
+                OpCode::CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.pop();
+                }
+                OpCode::Return => {
+                    let result = self.pop();
+                    self.close_upvalues(frame.base);
+                    self.stack.truncate(frame.base);
+
+                    match self.frames.pop() {
+                        Some(caller) => {
+                            *frame = caller;
+                            self.push(result);
+                        }
+                        None => return Ok(()),
+                    }
+                }
+                OpCode::Class => {
+                    let name = frame.read_string();
+                    self.push(Value::Class(Rc::new(Class {
+                        name: name.to_string(),
+                        methods: RefCell::new(HashMap::new()),
+                    })));
+                }
+                OpCode::Inherit => {
+                    let Value::Class(superclass) = self.peek(1) else {
+                        return Err(error(frame, "Superclass must be a class"));
+                    };
+                    let Value::Class(subclass) = self.peek(0) else {
+                        unreachable!("Inherit always follows a class declaration")
+                    };
+
+                    // methods are copied down, since classes can't change after being declared
+                    let inherited = RefCell::borrow(&superclass.methods).clone();
+                    RefCell::borrow_mut(&subclass.methods).extend(inherited);
+                    self.pop();
+                }
+                OpCode::Method => {
+                    let name = frame.read_string();
+                    let Value::Closure(method) = self.pop() else {
+                        unreachable!("methods are always closures")
+                    };
+                    let Value::Class(class) = self.peek(0) else {
+                        unreachable!("methods are always attached to a class")
+                    };
+                    RefCell::borrow_mut(&class.methods).insert(name, method);
+                }
+                OpCode::BuildList => {
+                    let count = frame.read_short() as usize;
+                    let elements = self.stack.split_off(self.stack.len() - count);
+                    self.push(Value::List(Rc::new(RefCell::new(elements))));
+                }
+                OpCode::BuildString => {
+                    let count = frame.read_short() as usize;
+                    let parts = self.stack.split_off(self.stack.len() - count);
+                    let string: String = parts.iter().map(|part| part.to_string()).collect();
+                    self.push(Value::Str(Rc::from(string)));
+                }
+                OpCode::BuildMap => {
+                    let count = frame.read_short() as usize;
+                    let entries = self.stack.split_off(self.stack.len() - 2 * count);
+
+                    let mut map = OrderedMap::new();
+                    for entry in entries.chunks(2) {
+                        map.insert(map_key(frame, &entry[0])?, entry[1].clone());
+                    }
+                    self.push(Value::Map(Rc::new(RefCell::new(map))));
+                }
+                OpCode::Iter => {
+                    // maps and strings are iterated over a snapshot of their keys and characters
+                    let iterable = match self.pop() {
+                        iterable @ (Value::List(_) | Value::Range(..)) => iterable,
+                        Value::Map(map) => {
+                            let keys = RefCell::borrow(&map).keys().map(Value::from).collect();
+                            Value::List(Rc::new(RefCell::new(keys)))
+                        }
+                        Value::Str(s) => {
+                            let chars = s
+                                .chars()
+                                .map(|c| Value::Str(Rc::from(c.to_string())))
+                                .collect();
+                            Value::List(Rc::new(RefCell::new(chars)))
+                        }
+                        _ => {
+                            return Err(error(
+                                frame,
+                                "Can only iterate over lists, maps, strings and ranges",
+                            ))
+                        }
+                    };
+                    self.push(iterable);
+                }
+                OpCode::ForIter => {
+                    let slot = frame.base + frame.read_byte() as usize;
+                    let offset = frame.read_short() as usize;
+
+                    let Value::Number(Number::Int(i)) = self.stack[slot + 1] else {
+                        unreachable!("the index of an iteration is always an integer")
+                    };
+                    let item = match &self.stack[slot] {
+                        Value::List(list) => RefCell::borrow(list).get(i as usize).cloned(),
+                        Value::Range(start, end) => {
+                            (start + i < *end).then_some(Value::Number(Number::Int(start + i)))
+                        }
+                        _ => unreachable!("'Iter' only leaves lists and ranges"),
+                    };
+
+                    match item {
+                        Some(item) => {
+                            self.stack[slot + 1] = Value::Number(Number::Int(i + 1));
+                            self.push(item);
+                        }
+                        None => frame.ip += offset,
+                    }
+                }
+                OpCode::GetIndex => {
+                    let index = self.pop();
+                    let value = match self.pop() {
+                        Value::List(list) => {
+                            let list = RefCell::borrow(&list);
+                            list[list_slot(frame, &index, list.len())?].clone()
+                        }
+                        Value::Map(map) => {
+                            let key = map_key(frame, &index)?;
+                            match RefCell::borrow(&map).get(&key) {
+                                Some(value) => value.clone(),
+                                None => {
+                                    return Err(error(frame, &fmt!("Undefined key '{index}'")));
+                                }
+                            }
+                        }
+                        _ => return Err(error(frame, "Can only index lists and maps")),
+                    };
+                    self.push(value);
+                }
+                OpCode::SetIndex => {
+                    let value = self.pop();
+                    let index = self.pop();
+                    match self.pop() {
+                        Value::List(list) => {
+                            let mut elements = RefCell::borrow_mut(&list);
+                            let slot = list_slot(frame, &index, elements.len())?;
+                            elements[slot] = value.clone();
+                        }
+                        Value::Map(map) => {
+                            let key = map_key(frame, &index)?;
+                            RefCell::borrow_mut(&map).insert(key, value.clone());
+                        }
+                        _ => return Err(error(frame, "Can only index lists and maps")),
+                    }
+                    self.push(value);
+                }
+                OpCode::Try => {
+                    let offset = frame.read_short() as usize;
+                    self.handlers.push(Handler {
+                        ip: frame.ip + offset,
+                        frames: self.frames.len(),
+                        stack: self.stack.len(),
+                    });
+                }
+                OpCode::EndTry => {
+                    self.handlers.pop();
+                }
+                // a caught runtime error is raised again as it was
+                OpCode::Throw => {
+                    let value = self.pop();
+                    if let Value::Error(err) = &value {
+                        return Err(err.rethrown());
+                    }
+
+                    let (line, span) = frame.position();
+                    return Err(InterpErr::Thrown {
+                        line,
+                        span,
+                        value: ThrownValue::Value(value),
+                        trace: Vec::new(),
+                    });
+                }
+                OpCode::Caught => {
+                    let Value::Exception(exception) = self.pop() else {
+                        unreachable!("'Caught' always follows a handler")
+                    };
+                    self.push(exception.value.clone());
+                }
+                OpCode::Rethrow => {
+                    let Value::Exception(exception) = self.pop() else {
+                        unreachable!("'Rethrow' always follows a handler")
+                    };
+                    return Err(exception.error.clone());
+                }
+            }
+        }
+    }
+
+    fn call_value(&mut self, frame: &mut CallFrame, argc: usize) -> Result<(), InterpErr> {
+        self.call_named(frame, argc, Vec::new())
+    }
+
+    // the named arguments were already taken off the stack
+    fn call_named(
+        &mut self,
+        frame: &mut CallFrame,
+        argc: usize,
+        named: Vec<(String, Value)>,
+    ) -> Result<(), InterpErr> {
+        let callee = self.stack.len() - argc - 1;
+
+        match self.stack[callee].clone() {
+            Value::Closure(closure) => self.call(frame, closure, argc, named),
+            Value::Native(native) => {
+                self.bind(frame, &native.signature, argc, named, false)?;
+                let result = (native.function)(&self.stack[callee + 1..])
+                    .map_err(|msg| self.native_traced(error(frame, &msg), frame, &native.name))?;
+                self.stack.truncate(callee);
+                self.push(result);
+                Ok(())
+            }
+            Value::Class(class) => {
+                let instance = Rc::new(Instance {
+                    class: Rc::clone(&class),
+                    fields: RefCell::new(HashMap::new()),
+                });
+                self.stack[callee] = Value::Instance(instance);
+
+                let init = RefCell::borrow(&class.methods).get("init").cloned();
+                match init {
+                    Some(init) => self.call(frame, init, argc, named),
+                    None => self
+                        .bind(frame, &Signature::fixed(&[]), argc, named, true)
+                        .map(|_| ()),
+                }
+            }
+            Value::BoundMethod(bound) => {
+                self.stack[callee] = bound.receiver.clone();
+                self.call(frame, Rc::clone(&bound.method), argc, named)
+            }
+            Value::NativeMethod(method) => {
+                self.bind(frame, &method.signature, argc, named, false)?;
+                let result =
+                    call_native_method(&method, &self.stack[callee + 1..]).map_err(|msg| {
+                        let e = error_at(method.at, &msg);
+                        self.native_traced(e, frame, &fmt!("<native fn {}>", method.name))
+                    })?;
+                self.stack.truncate(callee);
+                self.push(result);
+                Ok(())
+            }
+            _ => Err(error(frame, "Can only call functions and classes")),
+        }
+    }
+
+    fn call(
+        &mut self,
+        frame: &mut CallFrame,
+        closure: Rc<Closure>,
+        argc: usize,
+        named: Vec<(String, Value)>,
+    ) -> Result<(), InterpErr> {
+        let base = self.stack.len() - argc - 1;
+        let missing = self.bind(frame, &closure.function.signature, argc, named, true)?;
+
+        if self.frames.len() + 1 >= FRAMES_MAX {
+            return Err(error(frame, "Stack overflow"));
+        }
+
+        let callee = CallFrame {
+            closure,
+            ip: 0,
+            base,
+            missing,
+        };
+        self.frames.push(std::mem::replace(frame, callee));
+        Ok(())
+    }
+
+    fn invoke_from_class(
+        &mut self,
+        frame: &mut CallFrame,
+        class: &Class,
+        name: &str,
+        property: usize,
+        argc: usize,
+    ) -> Result<(), InterpErr> {
+        let method = RefCell::borrow(&class.methods).get(name).cloned();
+        match method {
+            Some(method) => self.call(frame, method, argc, Vec::new()),
+            None => {
+                let at = frame.position_at(property);
+                Err(error_at(at, &fmt!("Undefined property '{name}'")))
+            }
+        }
+    }
+
+    // replaces the arguments on top of the stack with the values of the
+    // parameters they bind to, followed by the extra arguments when there is a
+    // rest parameter: collected in a list for functions, as they are for
+    // natives. Returns which parameters were left to their default
+    fn bind(
+        &mut self,
+        frame: &CallFrame,
+        signature: &Signature,
+        argc: usize,
+        named: Vec<(String, Value)>,
+        collect_rest: bool,
+    ) -> Result<Vec<bool>, InterpErr> {
+        if named.is_empty() && signature.exact() == Some(argc) {
+            return Ok(Vec::new());
+        }
+
+        let positional = self.stack.split_off(self.stack.len() - argc);
+        let args = signature
+            .bind(positional, named)
+            .map_err(|msg| error(frame, &msg))?;
+
+        let missing = args.slots.iter().map(Option::is_none).collect();
+        let slots = args.slots.into_iter();
+        self.stack
+            .extend(slots.map(|slot| slot.unwrap_or(Value::Null)));
+        if signature.rest && collect_rest {
+            self.push(Value::List(Rc::new(RefCell::new(args.rest))));
+        } else {
+            self.stack.extend(args.rest);
+        }
+        Ok(missing)
+    }
+
+    // binds the method to the receiver on top of the stack
+    fn bind_method(
+        &self,
+        frame: &CallFrame,
+        class: &Class,
+        name: &str,
+    ) -> Result<Value, InterpErr> {
+        match RefCell::borrow(&class.methods).get(name) {
+            Some(method) => Ok(Value::BoundMethod(Rc::new(BoundMethod {
+                receiver: self.peek(0).clone(),
+                method: Rc::clone(method),
+            }))),
+            None => Err(error(frame, &fmt!("Undefined property '{name}'"))),
+        }
+    }
+
+    // closures capturing the same variable must share the same upvalue
+    // every frame still on the stack is a call the error escaped from: the one
+    // being executed was called from the line its caller stopped at
+    fn traced(&self, e: InterpErr, frame: &CallFrame) -> InterpErr {
+        e.traced(&self.call_stack(frame))
+    }
+
+    // natives run without a frame of their own, but are traced as if they had
+    // one, called from where the current frame stopped
+    fn native_traced(&self, e: InterpErr, frame: &CallFrame, name: &str) -> InterpErr {
+        let mut stack = self.call_stack(frame);
+        stack.push(TraceFrame::new(name.to_string(), frame.position().0));
+        e.traced(&stack)
+    }
+
+    // the calls in progress, outermost first
+    fn call_stack(&self, frame: &CallFrame) -> Vec<TraceFrame> {
+        let callees = std::iter::once(frame).chain(self.frames.iter().rev());
+        let mut stack: Vec<_> = callees
+            .zip(self.frames.iter().rev())
+            .map(|(callee, caller)| {
+                let name = fmt!("<fn {}>", callee.closure.function.name);
+                TraceFrame::new(name, caller.position().0)
+            })
+            .collect();
+        stack.reverse();
+        stack
+    }
+
+    fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+        let existing = self
+            .open_upvalues
+            .iter()
+            .find(|upvalue| matches!(*RefCell::borrow(upvalue), Upvalue::Open(s) if s == slot));
+
+        if let Some(upvalue) = existing {
+            return Rc::clone(upvalue);
+        }
+
+        let upvalue = Rc::new(RefCell::new(Upvalue::Open(slot)));
+        self.open_upvalues.push(Rc::clone(&upvalue));
+        upvalue
+    }
+
+    // moves every variable at or above 'from' out of the stack and into its upvalue
+    fn close_upvalues(&mut self, from: usize) {
+        let stack = &self.stack;
+        self.open_upvalues.retain(|upvalue| {
+            let mut upvalue = RefCell::borrow_mut(upvalue);
+            match *upvalue {
+                Upvalue::Open(slot) if slot >= from => {
+                    *upvalue = Upvalue::Closed(stack[slot].clone());
+                    false
+                }
+                _ => true,
+            }
+        });
+    }
+
+    fn number_op(
+        &mut self,
+        frame: &CallFrame,
+        op: impl Fn(Number, Number) -> Value,
+    ) -> Result<(), InterpErr> {
+        let (Value::Number(l), Value::Number(r)) = (self.peek(1), self.peek(0)) else {
+            return Err(error(frame, "Operands must be number"));
+        };
+
+        let value = op(*l, *r);
+        self.pop();
+        self.pop();
+        self.push(value);
+        Ok(())
+    }
+
+    fn arithmetic_op(&mut self, frame: &CallFrame, op: ArithmeticFn) -> Result<(), InterpErr> {
+        let (Value::Number(l), Value::Number(r)) = (self.peek(1), self.peek(0)) else {
+            return Err(error(frame, "Operands must be number"));
+        };
+
+        let value = op(*l, *r).map_err(|msg| error(frame, msg))?;
+        self.pop();
+        self.pop();
+        self.push(Value::Number(value));
+        Ok(())
+    }
+
+    // bitwise operators, see 'integer_op'
+    fn integer_op(&mut self, frame: &CallFrame, op: IntegerFn) -> Result<(), InterpErr> {
+        let (Value::Number(l), Value::Number(r)) = (self.peek(1), self.peek(0)) else {
+            return Err(error(frame, "Operands must be number"));
+        };
+
+        let value = integer_op(*l, *r, op).map_err(|msg| error(frame, msg))?;
+        self.pop();
+        self.pop();
+        self.push(Value::Number(value));
+        Ok(())
+    }
+
+    fn push(&mut self, value: Value) {
+        self.stack.push(value);
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack
+            .pop()
+            .expect("the compiler never pops an empty stack")
+    }
+
+    fn peek(&self, distance: usize) -> &Value {
+        &self.stack[self.stack.len() - 1 - distance]
+    }
+}
+
+fn error(frame: &CallFrame, msg: &str) -> InterpErr {
+    error_at(frame.position(), msg)
+}
+
+fn error_at((line, span): (usize, Span), msg: &str) -> InterpErr {
+    InterpErr::RuntimeError {
+        line,
+        span,
+        msg: msg.to_string(),
+        trace: Vec::new(),
+    }
+}
+
+fn error_field(at: (usize, Span), err: &CaughtError, name: &str) -> Result<Value, InterpErr> {
+    match name {
+        "message" => Ok(Value::Str(Rc::from(err.msg.as_str()))),
+        "line" => Ok(Value::Number(Number::Int(err.line as i64))),
+        _ => Err(error_at(at, &fmt!("Undefined property '{name}'"))),
+    }
+}
+
+fn bind_native_method(at: (usize, Span), receiver: Value, name: &str) -> Result<Value, InterpErr> {
+    let methods = match receiver {
+        Value::Map(_) => MAP_METHODS,
+        _ => LIST_METHODS,
+    };
+
+    match methods.iter().find(|(method, _)| *method == name) {
+        Some(&(name, params)) => Ok(Value::NativeMethod(Rc::new(NativeMethod {
+            receiver,
+            name,
+            signature: Signature::fixed(params),
+            at,
+        }))),
+        None => Err(error_at(at, &fmt!("Undefined property '{name}'"))),
+    }
+}
+
+// mirrors 'NativeMethod::call' of the tree walker
+fn call_native_method(method: &NativeMethod, args: &[Value]) -> Result<Value, String> {
+    match &method.receiver {
+        Value::List(list) => list_method(method.name, &mut RefCell::borrow_mut(list), args),
+        Value::Map(map) => map_method(method.name, &mut RefCell::borrow_mut(map), args),
+        _ => unreachable!("only lists and maps have native methods"),
+    }
+}
+
+fn list_method(name: &str, list: &mut Vec<Value>, args: &[Value]) -> Result<Value, String> {
+    let index = |index: &Value, len: usize, slot: SlotFn| match index {
+        Value::Number(Number::Int(n)) => slot(*n, len),
+        _ => Err("List index must be an integer"),
+    };
+
+    match name {
+        "push" => {
+            list.push(args[0].clone());
+            Ok(Value::Null)
+        }
+        "pop" => list
+            .pop()
+            .ok_or_else(|| "Can't pop from an empty list".to_string()),
+        "len" => Ok(Value::Number(Number::Int(list.len() as i64))),
+        "insert" => {
+            let at = index(&args[0], list.len(), insert_index)?;
+            list.insert(at, args[1].clone());
+            Ok(Value::Null)
+        }
+        "remove" => {
+            let at = index(&args[0], list.len(), list_index)?;
+            Ok(list.remove(at))
+        }
+        "slice" => match (&args[0], &args[1]) {
+            (Value::Number(Number::Int(start)), Value::Number(Number::Int(end))) => {
+                let (start, end) = slice_bounds(*start, *end, list.len());
+                Ok(Value::List(Rc::new(RefCell::new(
+                    list[start..end].to_vec(),
+                ))))
+            }
+            _ => Err("Slice bounds must be integers".to_string()),
+        },
+        _ => unreachable!("list methods are checked when bound"),
+    }
+}
+
+fn map_method(name: &str, map: &mut OrderedMap<Value>, args: &[Value]) -> Result<Value, String> {
+    match name {
+        "has" => Ok(Value::Bool(map.contains(&args[0].to_key()?))),
+        "remove" => map
+            .remove(&args[0].to_key()?)
+            .ok_or_else(|| fmt!("Undefined key '{}'", args[0])),
+        "keys" => {
+            let keys = map.keys().map(Value::from).collect();
+            Ok(Value::List(Rc::new(RefCell::new(keys))))
+        }
+        "values" => {
+            let values = map.values().cloned().collect();
+            Ok(Value::List(Rc::new(RefCell::new(values))))
+        }
+        "len" => Ok(Value::Number(Number::Int(map.len() as i64))),
+        _ => unreachable!("map methods are checked when bound"),
+    }
+}
+
+fn list_slot(frame: &CallFrame, index: &Value, len: usize) -> Result<usize, InterpErr> {
+    let slot = match index {
+        Value::Number(Number::Int(n)) => list_index(*n, len),
+        _ => Err("List index must be an integer"),
+    };
+    slot.map_err(|msg| error(frame, msg))
+}
+
+fn map_key(frame: &CallFrame, key: &Value) -> Result<MapKey, InterpErr> {
+    key.to_key().map_err(|msg| error(frame, msg))
+}
+
+impl Value {
+    // same rules as 'LoxObject::to_key'
+    fn to_key(&self) -> Result<MapKey, &'static str> {
+        match self {
+            Value::Number(n) => MapKey::number(*n),
+            Value::Str(s) => Ok(MapKey::Str(s.to_string())),
+            Value::Bool(b) => Ok(MapKey::Bool(*b)),
+            Value::Null => Ok(MapKey::Null),
+            _ => Err(KEY_ERROR),
+        }
+    }
+}
+
+impl From<&MapKey> for Value {
+    fn from(key: &MapKey) -> Self {
+        match key {
+            MapKey::Int(n) => Value::Number(Number::Int(*n)),
+            MapKey::Float(bits) => Value::Number(Number::Float(f64::from_bits(*bits))),
+            MapKey::Str(s) => Value::Str(Rc::from(s.as_str())),
+            MapKey::Bool(b) => Value::Bool(*b),
+            MapKey::Null => Value::Null,
+        }
+    }
+}
+
+fn truthy(v: &Value) -> bool {
+    match v {
+        Value::Bool(b) => *b,
+        Value::Null => false,
+        _ => true,
+    }
+}
+
+fn clock(_: &[Value]) -> Result<Value, String> {
+    Ok(Value::Number(Number::Float(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as f64,
+    )))
+}
+
+fn range(args: &[Value]) -> Result<Value, String> {
+    match (&args[0], &args[1]) {
+        (Value::Number(Number::Int(start)), Value::Number(Number::Int(end))) => {
+            Ok(Value::Range(*start, *end))
+        }
+        _ => Err("Range bounds must be integers".to_string()),
+    }
+}
+
+fn print(args: &[Value]) -> Result<Value, String> {
+    print!("{}", print_line(args)?);
+    Ok(Value::Null)
+}
+
+fn eprint(args: &[Value]) -> Result<Value, String> {
+    eprint!("{}", print_line(args)?);
+    Ok(Value::Null)
+}
+
+// mirrors 'print_line' of the tree walker
+fn print_line(args: &[Value]) -> Result<String, String> {
+    let option = |arg: &Value, name: &str| match arg {
+        Value::Null => Ok(None),
+        Value::Str(s) => Ok(Some(Rc::clone(s))),
+        _ => Err(fmt!("'{name}' must be a string")),
+    };
+    let (sep, end) = (option(&args[0], "sep")?, option(&args[1], "end")?);
+    Ok(join_line(&args[2..], sep.as_deref(), end.as_deref()))
+}
+
+// same rules as 'PartialEq for LoxObject': functions are never equal
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Class(a), Value::Class(b)) => Rc::ptr_eq(a, b),
+            (Value::Instance(a), Value::Instance(b)) => Rc::ptr_eq(a, b),
+            (Value::List(a), Value::List(b)) => *RefCell::borrow(a) == *RefCell::borrow(b),
+            (Value::Map(a), Value::Map(b)) => *RefCell::borrow(a) == *RefCell::borrow(b),
+            (Value::Range(a, b), Value::Range(c, d)) => a == c && b == d,
+            (Value::Error(a), Value::Error(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Number(n) => write!(f, "{n}"),
+            Value::Str(s) => write!(f, "{s}"),
+            Value::Closure(c) => write!(f, "<fn {}>", c.function.name),
+            Value::Native(n) => write!(f, "{}", n.name),
+            Value::Class(c) => write!(f, "<class {}>", c.name),
+            Value::Instance(i) => write!(f, "<{} instance>", i.class.name),
+            Value::BoundMethod(b) => write!(f, "<fn {}>", b.method.function.name),
+            Value::List(l) => {
+                let elements: Vec<_> = RefCell::borrow(l).iter().map(|e| e.to_string()).collect();
+                write!(f, "[{}]", elements.join(", "))
+            }
+            Value::Map(m) => {
+                let entries: Vec<_> = RefCell::borrow(m)
+                    .iter()
+                    .map(|(key, value)| fmt!("{}: {value}", Value::from(key)))
+                    .collect();
+                write!(f, "{{{}}}", entries.join(", "))
+            }
+            Value::Range(start, end) => write!(f, "range({start}, {end})"),
+            Value::NativeMethod(m) => write!(f, "<native fn {}>", m.name),
+            Value::Error(e) => write!(f, "{}", e.msg),
+            Value::Exception(e) => write!(f, "{}", e.value),
+        }
+    }
+}
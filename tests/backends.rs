@@ -0,0 +1,185 @@
+mod common;
+
+use common::{error, output, run, run_both};
+use format as fmt;
+
+// the VM is checked against the tree walker: both must print the same output
+// and report the same errors for every program
+const PROGRAMS: &[&str] = &[
+    // closures and recursion
+    "
+    fn counter() {
+        let n = 0;
+        return fn () { n += 1; return n; };
+    }
+    let c = counter();
+    c(); c();
+    print(c());
+    fn fib(n) { return n < 2 ? n : fib(n - 1) + fib(n - 2); }
+    print(fib(15));
+    ",
+    // classes and inheritance
+    "
+    class Shape {
+        init(name) { this.name = name; }
+        area() { return 0; }
+        describe() { return \"${this.name}: ${this.area()}\"; }
+    }
+    class Square < Shape {
+        init(side) { super.init(\"square\"); this.side = side; }
+        area() { return this.side ** 2; }
+    }
+    print(Square(3).describe());
+    let s = Square(2);
+    let area = s.area;
+    print(area(), s);
+    ",
+    // collections and loops
+    "
+    let xs = [3, 1, 2];
+    xs.push(4);
+    let total = 0;
+    outer: for x in xs {
+        for y in range(0, x) {
+            if y == 2 { continue outer; }
+            total += y;
+        }
+    }
+    let m = {\"a\": 1, 2: [xs.len()], 1.0: null};
+    for k in m { print(k, m[k]); }
+    print(total, xs.slice(1, 3), m.keys(), m.has(1));
+    let i = 0;
+    while true { i++; if i >= 5 { break; } }
+    print(i);
+    for c in \"ab\" { print(c); }
+    ",
+    // parameters and natives
+    "
+    fn f(a, b = a * 2, ...rest) { return [a, b, rest]; }
+    print(f(1), f(1, b: 5), f(1, 2, 3, 4));
+    print(\"a\", \"b\", sep: \"-\", end: \"!\\n\");
+    let g = (x) => x + 1;
+    print(g(g(1)));
+    ",
+    // numbers and operators
+    "
+    print(7 // 2, -7 // 2, 7 % -3, 2 ** 10, 2 ** -1, 7 / 2, 1 + 2.0);
+    print(5 & 3, 5 | 3, 5 ^ 3, ~5, 1 << 4, -16 >> 2, 0xff, 1_000, 1e3);
+    let n = null;
+    print(n ?? \"default\", false ?? 1, 1 < 2 ? \"yes\" : \"no\");
+    print(-9223372036854775808, 9223372036854775807);
+    ",
+    // exceptions
+    "
+    fn risky(n) {
+        try {
+            if n == 0 { throw \"zero\"; }
+            if n == 1 { return [].pop(); }
+            return n;
+        } finally {
+            print(\"cleanup\", n);
+        }
+    }
+    for n in range(0, 3) {
+        try { print(risky(n)); } catch (e) { print(\"caught\", e); }
+    }
+    try { try { 1 + \"a\"; } catch (e) { throw e; } } catch (e) { print(e.line); }
+    ",
+    // runtime errors and their tracebacks
+    "fn f(x) { return x.nope; }\nfn g() { return f(1); }\ng();",
+    "let xs = [1];\nfn f() { xs.insert(5, 1); }\nf();",
+    "class A { m() { return this.missing(); } }\nA().m();",
+    "print(\"x\", sep: 1);",
+    "let m = {};\nm[[1]] = 2;",
+    "fn f(a) {}\nf(1, 2);",
+    "9223372036854775807 + 1;",
+    "throw {\"code\": 1};",
+    "fn f() { f(); }\nf();",
+    // compile errors
+    "return 1;",
+    "class A < A {}",
+    "{ let a = a; }",
+    "break;",
+];
+
+#[test]
+fn backends_agree() {
+    for program in PROGRAMS {
+        run_both(program);
+    }
+}
+
+#[test]
+fn programs_run() {
+    for program in &PROGRAMS[..6] {
+        let run = run(program, true);
+        assert_eq!(run.code, Some(0), "{program:?} failed:\n{}", run.stderr);
+    }
+}
+
+#[test]
+fn deep_recursion_is_a_runtime_error() {
+    let run = run_both("fn f(n) { return f(n + 1) + 1; }\nf(0);");
+    assert_eq!(run.code, Some(65));
+    assert!(run.stderr.starts_with("runtime error: Stack overflow\n"));
+}
+
+#[test]
+fn calls_are_limited_to_255_arguments() {
+    let args = |n| vec!["1"; n].join(", ");
+    let source = fmt!("fn f(...a) {{ return a.len(); }}\nprint(f({}));", args(255));
+    assert_eq!(output(&source), "255\n");
+    assert_eq!(
+        error(&fmt!("print({});", args(256))),
+        "syntax error: functions only accept a maximum of 255 arguments"
+    );
+
+    let params = (0..256).map(|i| fmt!("p{i}")).collect::<Vec<_>>();
+    assert_eq!(
+        error(&fmt!("fn f({}) {{}}", params.join(", "))),
+        "syntax error: Cant have more than 255 parameters"
+    );
+    let source = fmt!(
+        "fn f({}) {{ return p254; }}\nprint(f({}));",
+        params[..255].join(", "),
+        args(255)
+    );
+    assert_eq!(output(&source), "1\n");
+}
+
+// limits of the bytecode the tree walker doesn't have
+#[test]
+fn chunk_limits_are_compile_errors() {
+    let locals: String = (0..300).map(|i| fmt!("let v{i} = {i}; ")).collect();
+    let source = fmt!("{{ {locals}print(v299); }}");
+    assert_eq!(output_of(&source, false), "299\n");
+    assert_eq!(
+        first_line(&run(&source, true)),
+        "syntax error: Too many local variables in function"
+    );
+
+    let constants: String = (0..70_000).map(|i| fmt!("x = {i}.5;\n")).collect();
+    let source = fmt!("let x = 0;\n{constants}");
+    assert_eq!(
+        first_line(&run(&source, true)),
+        "syntax error: Too many constants in one chunk"
+    );
+
+    let source = fmt!("print([{}].len());", vec!["null"; 70_000].join(", "));
+    assert_eq!(output_of(&source, false), "70000\n");
+    assert_eq!(
+        first_line(&run(&source, true)),
+        "syntax error: Too many entries in list literal"
+    );
+}
+
+fn output_of(source: &str, vm: bool) -> String {
+    let run = run(source, vm);
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    run.stdout
+}
+
+fn first_line(run: &common::Run) -> &str {
+    assert_eq!(run.code, Some(65));
+    run.stderr.lines().next().unwrap()
+}
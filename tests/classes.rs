@@ -0,0 +1,161 @@
+mod common;
+
+use common::{error, output};
+
+#[test]
+fn instances_have_fields_and_methods() {
+    let source = "
+        class Point {
+            init(x, y) { this.x = x; this.y = y; }
+            sum() { return this.x + this.y; }
+        }
+        let p = Point(1, 2);
+        p.x = 10;
+        print(p.sum(), p.y);
+    ";
+    assert_eq!(output(source), "12 2\n");
+}
+
+#[test]
+fn methods_are_bound_to_their_instance() {
+    let source = "
+        class Counter {
+            init() { this.n = 0; }
+            inc() { this.n += 1; return this.n; }
+        }
+        let c = Counter();
+        let inc = c.inc;
+        inc();
+        print(inc(), c.n);
+    ";
+    assert_eq!(output(source), "2 2\n");
+}
+
+#[test]
+fn fields_shadow_methods() {
+    let source = "
+        class A { m() { return 1; } }
+        let a = A();
+        a.m = fn () { return 2; };
+        print(a.m());
+    ";
+    assert_eq!(output(source), "2\n");
+}
+
+#[test]
+fn init_returns_the_instance() {
+    let source = "
+        class A { init() { this.a = 1; return; } }
+        let a = A();
+        print(a.init().a);
+    ";
+    assert_eq!(output(source), "1\n");
+}
+
+#[test]
+fn classes_are_called_with_the_arity_of_init() {
+    assert_eq!(
+        error("class A { init(a) {} }\nA();"),
+        "runtime error: Expected 1 arguments, but 0 where provided"
+    );
+    assert_eq!(
+        error("class A {}\nA(1);"),
+        "runtime error: Expected 0 arguments, but 1 where provided"
+    );
+}
+
+#[test]
+fn property_errors() {
+    assert_eq!(
+        error("class A {}\nA().x;"),
+        "runtime error: Undefined property 'x'"
+    );
+    assert_eq!(
+        error("let a = 1;\na.x;"),
+        "runtime error: Only instances have properties"
+    );
+    assert_eq!(
+        error("let a = true;\na.x = 1;"),
+        "runtime error: Only instances have fields"
+    );
+    assert_eq!(
+        error("\"a\"();"),
+        "runtime error: Can only call functions and classes"
+    );
+}
+
+#[test]
+fn class_syntax_errors() {
+    assert_eq!(error("class {}"), "syntax error: Expected class name");
+    assert_eq!(
+        error("class A m() {}"),
+        "syntax error: Expected '{' before class body"
+    );
+    assert_eq!(
+        error("class A { m() {}"),
+        "syntax error: Expected '}' after class body"
+    );
+    assert_eq!(
+        error("class A {}\nA().1;"),
+        "syntax error: Expected property name after '.'"
+    );
+}
+
+#[test]
+fn methods_are_inherited_and_overridden() {
+    let source = "
+        class A {
+            name() { return \"A\"; }
+            greet() { return \"hi from \" + this.name(); }
+        }
+        class B < A { name() { return \"B\"; } }
+        class C < B {}
+        print(C().greet(), A().greet());
+    ";
+    assert_eq!(output(source), "hi from B hi from A\n");
+}
+
+#[test]
+fn super_calls_the_method_of_the_superclass() {
+    let source = "
+        class A {
+            init(x) { this.x = x; }
+            m() { return \"A\" + this.x; }
+        }
+        class B < A {
+            init(x) { super.init(x + x); }
+            m() { return \"B\" + super.m(); }
+        }
+        class C < B { m() { let m = super.m; return \"C\" + m(); } }
+        print(C(\"1\").m());
+    ";
+    assert_eq!(output(source), "CBA11\n");
+}
+
+#[test]
+fn inheritance_errors() {
+    assert_eq!(
+        error("let A = 1;\nclass B < A {}"),
+        "runtime error: Superclass must be a class"
+    );
+    assert_eq!(
+        error("class A {}\nclass B < A { m() { return super.m(); } }\nB().m();"),
+        "runtime error: Undefined property 'm'"
+    );
+    assert_eq!(
+        error("class A < A {}"),
+        "syntax error: A class can't inherit from itself"
+    );
+    assert_eq!(
+        error("class A < {}"),
+        "syntax error: Expected superclass name"
+    );
+    assert_eq!(
+        error("class A < B { m() { super(); } }"),
+        "syntax error: Expected '.' after 'super'"
+    );
+    assert_eq!(
+        error("class A < B { m() { super.1; } }"),
+        "syntax error: Expected superclass method name"
+    );
+}
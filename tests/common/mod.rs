@@ -0,0 +1,94 @@
+// every test crate uses only some of the helpers
+#![allow(dead_code)]
+
+use interp::{
+    error::InterpErr,
+    lexer::Lexer,
+    token::{Token, TokenKind},
+};
+use std::{
+    process::Command,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+pub fn lex(source: &str) -> Vec<Token> {
+    match Lexer::new(source.to_string()).tokenized() {
+        Ok(tokens) => tokens,
+        Err(errors) => panic!("{source:?} didn't lex: {errors:?}"),
+    }
+}
+
+pub fn kinds(source: &str) -> Vec<TokenKind> {
+    lex(source).into_iter().map(|token| token.kind).collect()
+}
+
+pub fn lex_errors(source: &str) -> Vec<InterpErr> {
+    match Lexer::new(source.to_string()).tokenized() {
+        Ok(tokens) => panic!("{source:?} lexed to {tokens:?}"),
+        Err(errors) => errors,
+    }
+}
+
+// the message of the only error lexing the source gives
+pub fn lex_error(source: &str) -> String {
+    let errors = lex_errors(source);
+    match &errors[..] {
+        [InterpErr::SyntaxError { msg, .. }] => msg.clone(),
+        _ => panic!("{source:?} should give a single syntax error, got {errors:?}"),
+    }
+}
+
+// what running a script printed, with the path of the script in the reports
+// written as 'script.lox'
+#[derive(Debug, PartialEq)]
+pub struct Run {
+    pub stdout: String,
+    pub stderr: String,
+    pub code: Option<i32>,
+}
+
+pub fn run(source: &str, vm: bool) -> Run {
+    static SCRIPTS: AtomicUsize = AtomicUsize::new(0);
+    let id = SCRIPTS.fetch_add(1, Ordering::Relaxed);
+    let path = std::env::temp_dir().join(format!("lox-{}-{id}.lox", std::process::id()));
+    std::fs::write(&path, source).unwrap();
+
+    let mut command = Command::new(env!("CARGO_BIN_EXE_interp"));
+    if vm {
+        command.arg("--vm");
+    }
+    let output = command.arg(&path).output().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let path = path.to_str().unwrap();
+    Run {
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr)
+            .unwrap()
+            .replace(path, "script.lox"),
+        code: output.status.code(),
+    }
+}
+
+// the tree walker and the VM must agree on everything a script does
+pub fn run_both(source: &str) -> Run {
+    let tree_walker = run(source, false);
+    let vm = run(source, true);
+    assert_eq!(tree_walker, vm, "the backends disagree on {source:?}");
+    tree_walker
+}
+
+// what a script that runs without errors prints
+pub fn output(source: &str) -> String {
+    let run = run_both(source);
+    assert_eq!(run.code, Some(0), "{source:?} failed:\n{}", run.stderr);
+    run.stdout
+}
+
+// the first line of the report of a script that fails, like
+// "runtime error: Undefined variable 'x'"
+pub fn error(source: &str) -> String {
+    let run = run_both(source);
+    assert_eq!(run.code, Some(65), "{source:?} didn't fail");
+    run.stderr.lines().next().unwrap().to_string()
+}
@@ -0,0 +1,185 @@
+mod common;
+
+use interp::{
+    diagnostic::Diagnostic,
+    error::{InterpErr, TraceFrame},
+    token::Span,
+};
+
+#[test]
+fn repeated_frames_are_collapsed() {
+    let source = "fn f() { f(); }\nf();";
+    let mut trace = vec![TraceFrame::new("<fn f>".to_string(), 1); 100];
+    trace.push(TraceFrame::new("<fn f>".to_string(), 2));
+    let err = InterpErr::RuntimeError {
+        line: 1,
+        span: Span::new(9, 12),
+        msg: "Stack overflow".to_string(),
+        trace,
+    };
+
+    let report = Diagnostic::new("script.lox", source)
+        .with_color(false)
+        .render(&err);
+    let traceback = report.split_once("traceback").unwrap().1;
+    assert_eq!(
+        traceback,
+        " (most recent call first):\n  line 1, in <fn f>\n  [previous line repeated 100 times]\n  line 2, in <script>\n"
+    );
+}
+
+#[test]
+fn recursion_tracebacks_are_collapsed() {
+    let source = "fn f(n) {\n  if n == 0 { return 1 // 0; }\n  return f(n - 1);\n}\nf(10);";
+    let run = common::run(source, false);
+    let traceback = run.stderr.split_once("traceback").unwrap().1;
+    assert_eq!(
+        traceback,
+        " (most recent call first):\n  line 2, in <fn f>\n  line 3, in <fn f>\n  [previous line repeated 9 times]\n  line 5, in <script>\n"
+    );
+}
+
+#[test]
+fn tracebacks_name_every_frame_innermost_first() {
+    let source = "class A {\n  m(xs) { return xs.insert(9, 1); }\n}\nfn f() {\n  return A().m([]);\n}\nlet g = (x) => f();\ng(1);";
+    let run = common::run_both(source);
+    let traceback = run.stderr.split_once("traceback").unwrap().1;
+    assert_eq!(
+        traceback,
+        " (most recent call first):\n  line 2, in <native fn insert>\n  line 2, in <fn m>\n  line 5, in <fn f>\n  line 7, in <fn lambda>\n  line 8, in <script>\n"
+    );
+}
+
+#[test]
+fn errors_at_the_top_level_have_no_traceback() {
+    let run = common::run_both(
+        "let a = 1;
+a();",
+    );
+    assert!(!run.stderr.contains("traceback"));
+}
+
+#[test]
+fn caught_errors_leave_no_frames_behind() {
+    let source = "fn f() { return 1 + nil; }
+fn g() { try { f(); } catch (e) {} }
+g();
+fn h() { [].pop(); }
+h();";
+    let run = common::run_both(source);
+    let traceback = run.stderr.split_once("traceback").unwrap().1;
+    assert_eq!(
+        traceback,
+        " (most recent call first):\n  line 4, in <native fn pop>\n  line 4, in <fn h>\n  line 5, in <script>\n"
+    );
+}
+
+#[test]
+fn stack_overflow_tracebacks_are_collapsed() {
+    let run = common::run("fn f() { f(); }\nf();", false);
+    assert!(
+        run.stderr.ends_with(
+            "  line 1, in <fn f>\n  [previous line repeated 4094 times]\n  line 2, in <script>\n"
+        ),
+        "{}",
+        run.stderr
+    );
+}
+
+#[test]
+fn errors_display_as_their_report() {
+    let err = InterpErr::SyntaxError {
+        line: 3,
+        span: Span::new(40, 41),
+        msg: "Expected Expression".to_string(),
+        place: ";".to_string(),
+    };
+    assert_eq!(
+        err.to_string(),
+        "syntax error: Expected Expression\n--> <source>:3\n"
+    );
+}
+
+fn render(source: &str, err: &InterpErr, color: bool) -> String {
+    Diagnostic::new("script.lox", source)
+        .with_color(color)
+        .render(err)
+}
+
+#[test]
+fn reports_underline_the_span_with_a_help_note() {
+    let source = "let a = 1;\nlet b = x + 1;";
+    let run = common::run_both(source);
+    assert_eq!(
+        run.stderr,
+        "runtime error: Undefined variable 'x'\n --> script.lox:2:9\n  |\n2 | let b = x + 1;\n  |         ^\n  = help: variables must be declared with 'let' before they are used\n"
+    );
+}
+
+#[test]
+fn help_notes_for_static_errors() {
+    let run = common::run_both("return 1;");
+    assert!(run
+        .stderr
+        .ends_with("  = help: 'return' can only be used inside a function\n"));
+}
+
+#[test]
+fn an_unexpected_end_of_file_points_after_the_last_token() {
+    let run = common::run_both("let a = 1\n\n");
+    assert_eq!(
+        run.stderr,
+        "syntax error: Expect ';' after declaration\n --> script.lox:1:10\n  |\n1 | let a = 1\n  |          ^\n"
+    );
+}
+
+#[test]
+fn uncaught_exceptions_are_reported_with_their_value() {
+    let run = common::run_both("fn f() { throw [1, \"a\"]; }\nf();");
+    assert_eq!(
+        run.stderr.lines().next(),
+        Some("uncaught exception: [1, a]")
+    );
+}
+
+#[test]
+fn colors_are_optional() {
+    let source = "let a = b;";
+    let err = InterpErr::RuntimeError {
+        line: 1,
+        span: Span::new(8, 9),
+        msg: "Undefined variable 'b'".to_string(),
+        trace: Vec::new(),
+    };
+    let plain = render(source, &err, false);
+    let colored = render(source, &err, true);
+    assert!(!plain.contains('\x1b'));
+    assert!(colored.starts_with("\x1b[1;31mruntime error\x1b[0m: "));
+
+    let stripped = colored
+        .split("\x1b[")
+        .enumerate()
+        .map(|(i, part)| {
+            if i == 0 {
+                part
+            } else {
+                part.split_once('m').unwrap().1
+            }
+        })
+        .collect::<String>();
+    assert_eq!(stripped, plain);
+}
+
+#[test]
+fn spans_outside_the_source_report_only_the_line() {
+    let err = InterpErr::RuntimeError {
+        line: 7,
+        span: Span::new(100, 104),
+        msg: "Undefined variable 'b'".to_string(),
+        trace: Vec::new(),
+    };
+    assert_eq!(
+        render("let a = 1;", &err, false),
+        "runtime error: Undefined variable 'b'\n--> script.lox:7\n"
+    );
+}
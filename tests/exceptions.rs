@@ -0,0 +1,110 @@
+mod common;
+
+use common::{error, output};
+use format as fmt;
+
+#[test]
+fn thrown_values_are_caught() {
+    let source = "
+        fn f(x) { throw x; }
+        try { f({\"code\": 1}); } catch (e) { print(e[\"code\"]); }
+        try { f(\"oops\"); print(\"unreachable\"); } catch (e) { print(e); }
+        try { try { f(1); } catch (e) { throw e + 1; } } catch (e) { print(e); }
+    ";
+    assert_eq!(output(source), "1\noops\n2\n");
+}
+
+#[test]
+fn runtime_errors_are_catchable_values() {
+    let source = "
+        try {
+            let a = 1;
+            a + \"b\";
+        } catch (e) {
+            print(e.message, e.line, e);
+        }
+    ";
+    assert_eq!(
+        output(source),
+        "Operand must be 'string' or 'number' 4 Operand must be 'string' or 'number'\n"
+    );
+}
+
+#[test]
+fn finally_always_runs() {
+    let source = "
+        fn f(n) {
+            try {
+                if n == 0 { return \"returned\"; }
+                if n == 1 { throw \"thrown\"; }
+            } finally {
+                print(\"finally\", n);
+            }
+            return \"done\";
+        }
+        print(f(0));
+        try { f(1); } catch (e) { print(e); }
+        print(f(2));
+        for x in [1, 2] { try { break; } finally { print(\"left loop\"); } }
+    ";
+    assert_eq!(
+        output(source),
+        "finally 0\nreturned\nfinally 1\nthrown\nfinally 2\ndone\nleft loop\n"
+    );
+}
+
+#[test]
+fn finally_replaces_how_the_try_ended() {
+    let source = "
+        fn f() { try { throw 1; } finally { return 2; } }
+        print(f());
+    ";
+    assert_eq!(output(source), "2\n");
+}
+
+#[test]
+fn errors_in_catch_propagate() {
+    let source = "
+        try {
+            try { throw 1; } catch (e) { throw 2; } finally { print(\"inner\"); }
+        } catch (e) {
+            print(e);
+        }
+    ";
+    assert_eq!(output(source), "inner\n2\n");
+}
+
+#[test]
+fn uncaught_throws_are_reported() {
+    assert_eq!(
+        error("fn f() { throw \"bad\"; }\nf();"),
+        "uncaught exception: bad"
+    );
+    assert_eq!(
+        error("try { throw 1; } finally { print(1); }"),
+        "uncaught exception: 1"
+    );
+    assert_eq!(
+        error("try { 1 + nil; } catch (e) { e.code; }"),
+        "runtime error: Undefined property 'code'"
+    );
+}
+
+#[test]
+fn exception_syntax_errors() {
+    for (source, msg) in [
+        ("throw 1", "Expected ';' after throw statement"),
+        ("try print(1);", "Expected '{' after 'try'"),
+        ("try {}", "Expected 'catch' or 'finally' after try block"),
+        ("try {} catch e {}", "Expected '(' after 'catch'"),
+        ("try {} catch (1) {}", "Expected identifier"),
+        ("try {} catch (e {}", "Expected ')' after catch variable"),
+        (
+            "try {} catch (e) print(e);",
+            "Expected '{' after catch variable",
+        ),
+        ("try {} finally print(1);", "Expected '{' after 'finally'"),
+    ] {
+        assert_eq!(error(source), fmt!("syntax error: {msg}"), "{source:?}");
+    }
+}
@@ -0,0 +1,204 @@
+mod common;
+
+use common::{error, output};
+use format as fmt;
+
+#[test]
+fn return_unwinds_loops_and_blocks() {
+    let source = "
+        fn find(xs, target) {
+            for x in xs {
+                let i = 0;
+                while true {
+                    { if x == target { return \"found ${x}\"; } }
+                    i += 1;
+                    if i > 2 { break; }
+                }
+            }
+            return \"missing\";
+        }
+        print(find([1, 2, 3], 2), find([], 1));
+        fn nothing() { return; }
+        fn fallthrough() {}
+        print(nothing(), fallthrough());
+    ";
+    assert_eq!(output(source), "found 2 missing\nnull null\n");
+}
+
+#[test]
+fn return_from_a_closure_returns_only_from_it() {
+    let source = "
+        fn outer() {
+            let inner = fn () { return 1; };
+            inner();
+            return 2;
+        }
+        print(outer());
+    ";
+    assert_eq!(output(source), "2\n");
+}
+
+#[test]
+fn return_is_not_an_error() {
+    let source = "
+        fn f() {
+            try { return \"tried\"; } catch (e) { return \"caught\"; }
+        }
+        print(f());
+    ";
+    assert_eq!(output(source), "tried\n");
+}
+
+#[test]
+fn return_at_the_top_level_is_rejected() {
+    assert_eq!(
+        error("print(1);\nreturn;"),
+        "syntax error: Can't return from top-level code"
+    );
+}
+
+#[test]
+fn function_expressions_and_lambdas_are_values() {
+    let source = "
+        fn apply(f, x) { return f(x); }
+        let double = fn (x) { return x * 2; };
+        print(apply(double, 2), apply((x) => x + 1, 2), apply((x) => { return x; }, 3));
+        print((() => \"called\")(), double);
+        let add = (a, b) => a + b;
+        print(add(2, 1));
+    ";
+    assert_eq!(output(source), "4 3 3\ncalled <fn lambda>\n3\n");
+}
+
+#[test]
+fn lambdas_close_over_their_scope() {
+    let source = "
+        fn make(n) { return (x) => x + n; }
+        let add2 = make(2);
+        let n = 100;
+        print(add2(1));
+    ";
+    assert_eq!(output(source), "3\n");
+}
+
+#[test]
+fn function_expression_errors() {
+    for (source, msg) in [
+        ("let f = fn x {};", "Expected '(' after 'fn'"),
+        (
+            "let f = fn () return 1;",
+            "Expected '{' before function body",
+        ),
+        ("let f = (a, 1) => a;", "Expected parameter name"),
+    ] {
+        assert_eq!(error(source), fmt!("syntax error: {msg}"), "{source:?}");
+    }
+}
+
+#[test]
+fn default_named_and_rest_parameters() {
+    let source = "
+        fn f(a, b = a + 1, c = 10) { return [a, b, c]; }
+        print(f(1), f(1, 5), f(1, c: 3), f(c: 3, a: 2));
+        fn g(a, ...rest) { return [a, rest]; }
+        print(g(1), g(1, 2, 3));
+        let h = (x = \"default\") => x;
+        print(h(), h(x: \"named\"));
+    ";
+    assert_eq!(
+        output(source),
+        "[1, 2, 10] [1, 5, 10] [1, 2, 3] [2, 3, 3]\n[1, []] [1, [2, 3]]\ndefault named\n"
+    );
+}
+
+#[test]
+fn defaults_are_evaluated_at_each_call() {
+    let source = "
+        fn f(xs = []) { xs.push(1); return xs; }
+        print(f(), f());
+    ";
+    assert_eq!(output(source), "[1] [1]\n");
+}
+
+#[test]
+fn arity_errors() {
+    for (source, msg) in [
+        (
+            "fn f(a) {}\nf();",
+            "Expected 1 arguments, but 0 where provided",
+        ),
+        (
+            "fn f(a, b = 1) {}\nf(1, 2, 3);",
+            "Expected 1 to 2 arguments, but 3 where provided",
+        ),
+        (
+            "fn f(a, ...r) {}\nf();",
+            "Expected at least 1 arguments, but 0 where provided",
+        ),
+        ("fn f(a) {}\nf(b: 1);", "Unexpected argument 'b'"),
+        ("fn f(a) {}\nf(1, a: 2);", "Argument 'a' passed twice"),
+        ("fn f(a, b) {}\nf(b: 1);", "Missing argument 'a'"),
+    ] {
+        assert_eq!(error(source), fmt!("runtime error: {msg}"), "{source:?}");
+    }
+}
+
+#[test]
+fn parameter_syntax_errors() {
+    for (source, msg) in [
+        ("fn f(...a, b) {}", "Rest parameter must be last"),
+        ("fn f(...a = []) {}", "Rest parameter can't have a default"),
+        (
+            "fn f(a = 1, b) {}",
+            "Expected default value for parameter after one with a default",
+        ),
+        ("fn f(1) {}", "Expected parameter name"),
+        (
+            "fn f(a, b) {}\nf(a: 1, 2);",
+            "Positional argument can't follow a named argument",
+        ),
+    ] {
+        assert_eq!(error(source), fmt!("syntax error: {msg}"), "{source:?}");
+    }
+}
+
+#[test]
+fn print_takes_any_number_of_values() {
+    let source = "
+        print();
+        print(1, \"a\", [true], null);
+        print(1, 2, sep: \", \");
+        print(\"no newline\", end: \"\");
+        print(\"!\", end: \"\\n\", sep: \"\");
+        let p = print;
+        p(\"aliased\", 1);
+    ";
+    assert_eq!(
+        output(source),
+        "\n1 a [true] null\n1, 2\nno newline!\naliased 1\n"
+    );
+}
+
+#[test]
+fn eprint_writes_to_stderr() {
+    let run = common::run_both("eprint(\"to\", \"stderr\", sep: \"-\");\nprint(\"to stdout\");");
+    assert_eq!(run.code, Some(0));
+    assert_eq!(run.stdout, "to stdout\n");
+    assert_eq!(run.stderr, "to-stderr\n");
+}
+
+#[test]
+fn print_options_must_be_strings() {
+    assert_eq!(
+        error("print(\"x\", sep: 1);"),
+        "runtime error: 'sep' must be a string"
+    );
+    assert_eq!(
+        error("eprint(end: 0);"),
+        "runtime error: 'end' must be a string"
+    );
+    assert_eq!(
+        error("print(\"x\", start: \"\");"),
+        "runtime error: Unexpected argument 'start'"
+    );
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use common::{kinds, lex_error};
+use interp::{number::Number, token::TokenKind};
+
+fn slashes(source: &str) -> usize {
+    kinds(source)
+        .iter()
+        .filter(|kind| **kind == TokenKind::SlashSlash)
+        .count()
+}
+
+#[test]
+fn comments_after_blocks() {
+    for source in [
+        "while (a) {} // spin forever",
+        "let m = {} // empty (for now)\n;",
+        "fn f() {\n} // done",
+    ] {
+        assert_eq!(slashes(source), 0, "{source:?}");
+    }
+}
+
+#[test]
+fn floor_division_between_operands() {
+    for source in [
+        "let q = 7 // 2;",
+        "print(a // b);",
+        "xs[i] // 2 + 1;",
+        "f(x) // (y);",
+        "[n // 2, n];",
+        "i++ // 2;",
+        "\"${a // b}\"",
+        "let q = a // b\n+ 1;",
+    ] {
+        assert_eq!(slashes(source), 1, "{source:?}");
+    }
+}
+
+#[test]
+fn chained_floor_divisions_lex_in_linear_time() {
+    let source = format!("a{};", " // 2".repeat(50_000));
+    assert_eq!(slashes(&source), 50_000);
+}
+
+#[test]
+fn comment_at_the_start_of_a_line() {
+    assert_eq!(
+        kinds("// 7 // 2;\nx"),
+        [TokenKind::Identifier, TokenKind::Eof]
+    );
+}
+
+#[test]
+fn number_literals() {
+    for (source, value) in [
+        ("0xFF", Number::Int(255)),
+        ("0xff_ff", Number::Int(65535)),
+        ("0b1010", Number::Int(10)),
+        ("0o17", Number::Int(15)),
+        ("1_000_000", Number::Int(1_000_000)),
+        ("9223372036854775807", Number::Int(i64::MAX)),
+        ("1.5", Number::Float(1.5)),
+        ("1e-9", Number::Float(1e-9)),
+        ("2E+3", Number::Float(2000.0)),
+        ("3.141_592", Number::Float(3.141592)),
+    ] {
+        assert_eq!(kinds(source)[0], TokenKind::Number(value), "{source:?}");
+    }
+}
+
+#[test]
+fn malformed_number_literals() {
+    for source in ["0x", "0b2", "0b102", "0o8", "0xG", "1e", "1e+", "12ab"] {
+        assert_eq!(lex_error(source), "Invalid number literal", "{source:?}");
+    }
+    for source in ["1__0", "1_", "0x_1", "1_.5", "1.5_", "1e_5"] {
+        assert_eq!(
+            lex_error(source),
+            "Digit separator must be between digits",
+            "{source:?}"
+        );
+    }
+}
+
+#[test]
+fn number_literals_too_large() {
+    for source in [
+        "9223372036854775809",
+        "0x8000000000000001",
+        "0b1_0000000000000000000000000000000000000000000000000000000000000000",
+        "1e400",
+    ] {
+        assert_eq!(
+            lex_error(source),
+            "Number literal is too large",
+            "{source:?}"
+        );
+    }
+}
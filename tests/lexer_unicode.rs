@@ -0,0 +1,181 @@
+mod common;
+
+use common::{lex, lex_errors};
+use interp::{
+    error::InterpErr,
+    number::Number,
+    token::{Span, Token, TokenKind},
+};
+
+fn identifiers(tokens: &[Token]) -> Vec<&str> {
+    tokens
+        .iter()
+        .filter(|token| token.kind == TokenKind::Identifier)
+        .map(|token| token.lexeme.as_str())
+        .collect()
+}
+
+fn strings(tokens: &[Token]) -> Vec<&str> {
+    tokens
+        .iter()
+        .filter_map(|token| match &token.kind {
+            TokenKind::String(value) => Some(value.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn identifiers_in_any_script() {
+    let tokens = lex("let π = 1; let 变量 = 2; let naïve_x = 3; let _ñ1 = 4; let Ωmega = 5;");
+    assert_eq!(
+        identifiers(&tokens),
+        ["π", "变量", "naïve_x", "_ñ1", "Ωmega"]
+    );
+}
+
+#[test]
+fn identifiers_continue_with_combining_marks() {
+    let tokens = lex("let e\u{301}t\u{301} = 1;");
+    assert_eq!(identifiers(&tokens), ["e\u{301}t\u{301}"]);
+}
+
+#[test]
+fn keywords_end_at_non_ascii_boundaries() {
+    let tokens = lex("let ñ=1; ñ");
+    let kinds: Vec<_> = tokens.iter().map(|token| token.kind.clone()).collect();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::Let,
+            TokenKind::Identifier,
+            TokenKind::Equal,
+            TokenKind::Number(Number::Int(1)),
+            TokenKind::Semicolon,
+            TokenKind::Identifier,
+            TokenKind::Eof,
+        ]
+    );
+}
+
+#[test]
+fn spans_are_byte_ranges_of_the_lexeme() {
+    let source = "let café = \"crème brûlée\"; // ünïcödé\nprint(café + \"🦀\");\nlet 数 = 1 + 2;";
+    for token in lex(source) {
+        assert_eq!(&source[token.span.start..token.span.end], token.lexeme);
+    }
+}
+
+#[test]
+fn columns_count_chars() {
+    let tokens = lex("let café = \"crème\";\n  é;");
+    let positions: Vec<_> = tokens
+        .iter()
+        .map(|token| (token.line, token.column, token.span))
+        .collect();
+    assert_eq!(
+        positions,
+        [
+            (1, 1, Span::new(0, 3)),
+            (1, 5, Span::new(4, 9)),
+            (1, 10, Span::new(10, 11)),
+            (1, 12, Span::new(12, 20)),
+            (1, 19, Span::new(20, 21)),
+            (2, 3, Span::new(24, 26)),
+            (2, 4, Span::new(26, 27)),
+            (2, 5, Span::new(27, 27)),
+        ]
+    );
+}
+
+#[test]
+fn strings_keep_multi_byte_chars() {
+    let tokens = lex(r#""café" "日本語" "🦀 crab" "ü\tü""#);
+    assert_eq!(strings(&tokens), ["café", "日本語", "🦀 crab", "ü\tü"]);
+}
+
+#[test]
+fn unicode_escapes_next_to_multi_byte_chars() {
+    let tokens = lex(r#""é\u{e9}é" "\u{1F980}🦀""#);
+    assert_eq!(strings(&tokens), ["ééé", "🦀🦀"]);
+}
+
+#[test]
+fn raw_and_triple_quoted_strings() {
+    let tokens = lex("r\"ç\\ñ\" \"\"\"\n    ünë\n      twö\n    \"\"\"");
+    assert_eq!(strings(&tokens), ["ç\\ñ", "ünë\n  twö"]);
+}
+
+#[test]
+fn interpolation_around_multi_byte_text() {
+    let tokens = lex("\"→ ${x} ←\"");
+    let kinds: Vec<_> = tokens.iter().map(|token| token.kind.clone()).collect();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::Interpolated("→ ".to_string()),
+            TokenKind::Identifier,
+            TokenKind::InterpolatedEnd(" ←".to_string()),
+            TokenKind::Eof,
+        ]
+    );
+}
+
+#[test]
+fn comments_with_multi_byte_chars_are_skipped() {
+    let tokens = lex("// ☃ snowman ☃\nlet x; // ✓\nx");
+    assert_eq!(identifiers(&tokens), ["x", "x"]);
+    assert_eq!(tokens.last().unwrap().line, 3);
+}
+
+#[test]
+fn unexpected_multi_byte_char() {
+    let errors = lex_errors("let x = 1 € 2;");
+    let [InterpErr::SyntaxError {
+        line,
+        span,
+        msg,
+        place,
+    }] = &errors[..]
+    else {
+        panic!("expected a single syntax error, got {errors:?}");
+    };
+    assert_eq!((*line, *span), (1, Span::new(10, 13)));
+    assert_eq!(
+        (msg.as_str(), place.as_str()),
+        ("Unexpected character", "€")
+    );
+}
+
+#[test]
+fn symbols_and_foreign_digits_are_not_identifiers_or_numbers() {
+    let errors = lex_errors("let 🦀 = ٣;");
+    let places: Vec<_> = errors
+        .iter()
+        .map(|e| match e {
+            InterpErr::SyntaxError { place, .. } => place.as_str(),
+            _ => panic!("expected syntax errors, got {e:?}"),
+        })
+        .collect();
+    assert_eq!(places, ["🦀", "٣"]);
+}
+
+#[test]
+fn unterminated_string_after_multi_byte_text() {
+    let errors = lex_errors("print(\"ünterminated");
+    let [InterpErr::SyntaxError { span, msg, .. }] = &errors[..] else {
+        panic!("expected a single syntax error, got {errors:?}");
+    };
+    assert_eq!(
+        (*span, msg.as_str()),
+        (Span::new(6, 20), "Unterminated string")
+    );
+}
+
+#[test]
+fn long_sources_lex_in_linear_time() {
+    let source = "let ñ = \"ü\"; // é\n".repeat(50_000);
+    let tokens = lex(&source);
+    assert_eq!(tokens.len(), 5 * 50_000 + 1);
+    assert_eq!(tokens.last().unwrap().line, 50_001);
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use common::{error, output};
+
+#[test]
+fn literals_indexing_and_assignment() {
+    let source = "
+        let xs = [1, \"two\", [3], ];
+        xs[0] = xs[0] + 10;
+        xs[-1][0] = 4;
+        print(xs, xs[1], xs[-1], []);
+    ";
+    assert_eq!(output(source), "[11, two, [4]] two [4] []\n");
+}
+
+#[test]
+fn lists_are_shared() {
+    let source = "
+        let a = [1];
+        let b = a;
+        b.push(2);
+        fn add(xs) { xs.push(3); }
+        add(a);
+        print(a, a == b, [1, [2]] == [1, [2]]);
+    ";
+    assert_eq!(output(source), "[1, 2, 3] true true\n");
+}
+
+#[test]
+fn native_methods() {
+    let source = "
+        let xs = [1, 2, 3];
+        xs.push(4);
+        print(xs.pop(), xs.len());
+        xs.insert(0, 0);
+        xs.insert(-1, 9);
+        print(xs);
+        print(xs.remove(1), xs);
+        print(xs.slice(1, 3), xs.slice(-2, 10), xs.slice(3, 1));
+    ";
+    assert_eq!(
+        output(source),
+        "4 3\n[0, 1, 2, 9, 3]\n1 [0, 2, 9, 3]\n[2, 9] [9, 3] []\n"
+    );
+}
+
+#[test]
+fn index_errors() {
+    assert_eq!(
+        error("let xs = [1, 2];\nxs[2];"),
+        "runtime error: List index out of bounds"
+    );
+    assert_eq!(
+        error("let xs = [1, 2];\nxs[-3] = 1;"),
+        "runtime error: List index out of bounds"
+    );
+    assert_eq!(
+        error("let xs = [1, 2];\nxs[0.0];"),
+        "runtime error: List index must be an integer"
+    );
+    assert_eq!(
+        error("let n = 1;\nn[0];"),
+        "runtime error: Can only index lists and maps"
+    );
+    assert_eq!(
+        error("let n = 1;\nn[0] = 1;"),
+        "runtime error: Can only index lists and maps"
+    );
+}
+
+#[test]
+fn native_method_errors() {
+    assert_eq!(
+        error("[].pop();"),
+        "runtime error: Can't pop from an empty list"
+    );
+    assert_eq!(
+        error("[1].remove(1);"),
+        "runtime error: List index out of bounds"
+    );
+    assert_eq!(
+        error("[1].slice(0, \"1\");"),
+        "runtime error: Slice bounds must be integers"
+    );
+    assert_eq!(
+        error("[1].sort();"),
+        "runtime error: Undefined property 'sort'"
+    );
+}
+
+#[test]
+fn list_syntax_errors() {
+    assert_eq!(
+        error("let xs = [1, 2;"),
+        "syntax error: Expected ']' after list elements"
+    );
+    assert_eq!(
+        error("let xs = [1];\nxs[0;"),
+        "syntax error: Expected ']' after index"
+    );
+}
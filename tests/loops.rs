@@ -0,0 +1,140 @@
+mod common;
+
+use common::{error, output};
+
+#[test]
+fn for_in_iterates_over_lists_maps_strings_and_ranges() {
+    let source = "
+        for x in [1, 2] { print(x); }
+        for k in {\"a\": 1, \"b\": 2} { print(k); }
+        for c in \"hé\" { print(c); }
+        for i in range(2, 4) { print(i); }
+        for i in range(3, 1) { print(i); }
+    ";
+    assert_eq!(output(source), "1\n2\na\nb\nh\né\n2\n3\n");
+}
+
+#[test]
+fn each_iteration_has_a_fresh_binding() {
+    let source = "
+        let fns = [];
+        for x in [1, 2, 3] { fns.push(() => x); }
+        print(fns[0](), fns[1](), fns[2]());
+    ";
+    assert_eq!(output(source), "1 2 3\n");
+}
+
+#[test]
+fn the_iterable_is_evaluated_once() {
+    let source = "
+        let n = 0;
+        fn items() { n += 1; return [1, 2, 3]; }
+        for x in items() {}
+        print(n);
+    ";
+    assert_eq!(output(source), "1\n");
+}
+
+#[test]
+fn c_style_loops_still_work() {
+    let source = "
+        let total = 0;
+        for let i = 0; i < 4; i = i + 1 { total = total + i; }
+        print(total);
+    ";
+    assert_eq!(output(source), "6\n");
+}
+
+#[test]
+fn iteration_errors() {
+    assert_eq!(
+        error("for x in 1 {}"),
+        "runtime error: Can only iterate over lists, maps, strings and ranges"
+    );
+    assert_eq!(
+        error("for x in range(0, 1.5) {}"),
+        "runtime error: Range bounds must be integers"
+    );
+}
+
+#[test]
+fn break_and_continue() {
+    let source = "
+        let i = 0;
+        while true {
+            i += 1;
+            if i == 2 { continue; }
+            if i == 4 { break; }
+            print(i);
+        }
+        for x in [1, 2, 3] { if x == 2 { break; } print(x); }
+    ";
+    assert_eq!(output(source), "1\n3\n1\n");
+}
+
+#[test]
+fn continue_runs_the_increment_of_c_style_loops() {
+    let source = "
+        for let i = 0; i < 5; i += 1 {
+            if i % 2 == 0 { continue; }
+            print(i);
+        }
+    ";
+    assert_eq!(output(source), "1\n3\n");
+}
+
+#[test]
+fn labels_jump_out_of_the_named_loop() {
+    let source = "
+        outer: for x in range(0, 3) {
+            inner: for y in range(0, 3) {
+                if y == 1 { continue outer; }
+                if x == 2 { break outer; }
+                print(x, y);
+            }
+        }
+        let n = 0;
+        outer: while true {
+            while true { n += 1; break outer; }
+        }
+        print(n);
+    ";
+    assert_eq!(output(source), "0 0\n1 0\n1\n");
+}
+
+#[test]
+fn break_leaves_closures_intact() {
+    let source = "
+        let fns = [];
+        for x in [1, 2, 3] {
+            fns.push(() => x);
+            if x == 2 { break; }
+        }
+        print(fns.len(), fns[1]());
+    ";
+    assert_eq!(output(source), "2 2\n");
+}
+
+#[test]
+fn jump_errors() {
+    assert_eq!(
+        error("break;"),
+        "syntax error: Can't use 'break' outside of a loop"
+    );
+    assert_eq!(
+        error("while true { fn f() { continue; } }"),
+        "syntax error: Can't use 'continue' outside of a loop"
+    );
+    assert_eq!(
+        error("a: while true { while true { break b; } }"),
+        "syntax error: No loop labelled 'b'"
+    );
+    assert_eq!(
+        error("a: print(1);"),
+        "syntax error: Only loops can be labelled"
+    );
+    assert_eq!(
+        error("while true { break }"),
+        "syntax error: Expected ';' after loop jump"
+    );
+}
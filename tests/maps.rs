@@ -0,0 +1,95 @@
+mod common;
+
+use common::{error, output};
+
+#[test]
+fn literals_keep_insertion_order() {
+    let source = "
+        let m = {\"b\": 1, 1: \"one\", true: [], null: 0, };
+        m[\"a\"] = 2;
+        m[\"b\"] = 3;
+        print(m, {});
+        print(m.keys(), m.values());
+    ";
+    assert_eq!(
+        output(source),
+        "{b: 3, 1: one, true: [], null: 0, a: 2} {}\n[b, 1, true, null, a] [3, one, [], 0, 2]\n"
+    );
+}
+
+#[test]
+fn integers_and_floats_are_the_same_key() {
+    let source = "
+        let m = {1: \"a\"};
+        m[1.0] = \"b\";
+        print(m[1], m.len(), m.has(1.0), m.has(\"1\"));
+    ";
+    assert_eq!(output(source), "b 1 true false\n");
+}
+
+#[test]
+fn blocks_and_maps_are_told_apart() {
+    let source = "
+        { let x = 1; print(x); }
+        {}
+        let m = {\"k\": {}};
+        print(m);
+    ";
+    assert_eq!(output(source), "1\n{k: {}}\n");
+}
+
+#[test]
+fn native_methods() {
+    let source = "
+        let m = {\"a\": 1, \"b\": 2, \"c\": 3};
+        print(m.remove(\"b\"), m, m.len(), m.has(\"b\"));
+        m[\"b\"] = 4;
+        print(m.keys());
+    ";
+    assert_eq!(output(source), "2 {a: 1, c: 3} 2 false\n[a, c, b]\n");
+}
+
+#[test]
+fn key_errors() {
+    assert_eq!(
+        error("let m = {};\nm[\"x\"];"),
+        "runtime error: Undefined key 'x'"
+    );
+    assert_eq!(
+        error("let m = {};\nm.remove(1);"),
+        "runtime error: Undefined key '1'"
+    );
+    assert_eq!(
+        error("let m = {};\nm[[1]] = 2;"),
+        "runtime error: Map keys must be numbers, strings, booleans or null"
+    );
+    assert_eq!(
+        error("let m = {{}: 1};"),
+        "runtime error: Map keys must be numbers, strings, booleans or null"
+    );
+    assert_eq!(
+        error("let m = {};\nm[0.0 / 0.0] = 1;"),
+        "runtime error: NaN can't be used as a map key"
+    );
+}
+
+#[test]
+fn map_syntax_errors() {
+    assert_eq!(
+        error("let m = {\"a\" 1};"),
+        "syntax error: Expected ':' after map key"
+    );
+    assert_eq!(
+        error("let m = {\"a\": 1;"),
+        "syntax error: Expected '}' after map entries"
+    );
+}
+
+#[test]
+fn entries_are_evaluated_before_keys_are_checked() {
+    let run = common::run_both("let m = {[1]: print(\"value\")};");
+    assert_eq!(run.stdout, "value\n");
+    assert!(run
+        .stderr
+        .contains("1 | let m = {[1]: print(\"value\")};\n  |         ^^^^^^"));
+}
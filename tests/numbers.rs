@@ -0,0 +1,116 @@
+use interp::number::{self, Number};
+
+fn shown(n: Result<Number, &str>) -> String {
+    match n {
+        Ok(n) => n.to_string(),
+        Err(msg) => panic!("expected a number, got {msg:?}"),
+    }
+}
+
+#[test]
+fn integers_and_floats_display_differently() {
+    assert_eq!(Number::Int(3).to_string(), "3");
+    assert_eq!(Number::Float(3.0).to_string(), "3.0");
+    assert_eq!(Number::Float(-0.5).to_string(), "-0.5");
+    assert_eq!(Number::Int(-7).to_string(), "-7");
+    assert_eq!(Number::Float(1e300).to_string(), "1e300");
+}
+
+#[test]
+fn integer_arithmetic_stays_integer() {
+    let (a, b) = (Number::Int(7), Number::Int(2));
+    assert_eq!(shown(number::add(a, b)), "9");
+    assert_eq!(shown(number::subtract(a, b)), "5");
+    assert_eq!(shown(number::multiply(a, b)), "14");
+    assert_eq!(shown(number::floor_divide(a, b)), "3");
+    assert_eq!(shown(number::modulo(a, b)), "1");
+    assert_eq!(shown(number::power(a, b)), "49");
+}
+
+#[test]
+fn a_float_operand_promotes_the_result() {
+    let (i, f) = (Number::Int(3), Number::Float(1.5));
+    assert_eq!(shown(number::add(i, f)), "4.5");
+    assert_eq!(shown(number::add(f, i)), "4.5");
+    assert_eq!(shown(number::subtract(i, Number::Float(1.0))), "2.0");
+    assert_eq!(shown(number::multiply(i, Number::Float(2.0))), "6.0");
+    assert_eq!(shown(number::floor_divide(Number::Float(7.0), i)), "2.0");
+    assert_eq!(
+        shown(number::modulo(Number::Int(-7), Number::Float(3.0))),
+        "2.0"
+    );
+    assert_eq!(shown(number::power(Number::Int(2), Number::Int(-1))), "0.5");
+}
+
+#[test]
+fn division_always_makes_a_float() {
+    assert_eq!(shown(number::divide(Number::Int(6), Number::Int(3))), "2.0");
+    assert_eq!(shown(number::divide(Number::Int(1), Number::Int(0))), "inf");
+}
+
+#[test]
+fn floor_division_and_modulo_round_down() {
+    let (a, b) = (Number::Int(-7), Number::Int(2));
+    assert_eq!(shown(number::floor_divide(a, b)), "-4");
+    assert_eq!(shown(number::modulo(a, b)), "1");
+    assert_eq!(shown(number::modulo(Number::Int(7), Number::Int(-2))), "-1");
+}
+
+#[test]
+fn integer_overflow_is_an_error() {
+    let (max, min, one) = (Number::Int(i64::MAX), Number::Int(i64::MIN), Number::Int(1));
+    assert_eq!(number::add(max, one), Err("Integer overflow"));
+    assert_eq!(number::subtract(min, one), Err("Integer overflow"));
+    assert_eq!(
+        number::multiply(max, Number::Int(2)),
+        Err("Integer overflow")
+    );
+    assert_eq!(
+        number::power(Number::Int(2), Number::Int(63)),
+        Err("Integer overflow")
+    );
+    assert_eq!(
+        number::floor_divide(min, Number::Int(-1)),
+        Err("Integer overflow")
+    );
+    assert_eq!(min.negate(), Err("Integer overflow"));
+    assert_eq!(
+        shown(number::add(max, Number::Float(1.0))),
+        "9.223372036854776e18"
+    );
+}
+
+#[test]
+fn integer_division_by_zero_is_an_error() {
+    let zero = Number::Int(0);
+    assert_eq!(
+        number::floor_divide(Number::Int(1), zero),
+        Err("Division by zero")
+    );
+    assert_eq!(
+        number::modulo(Number::Int(1), zero),
+        Err("Division by zero")
+    );
+    assert_eq!(shown(number::floor_divide(Number::Float(1.0), zero)), "inf");
+}
+
+#[test]
+fn integers_and_floats_compare_by_value() {
+    assert_eq!(Number::Int(1), Number::Float(1.0));
+    assert_ne!(Number::Int(1), Number::Float(1.5));
+    assert!(Number::Int(1) < Number::Float(1.5));
+    assert_ne!(Number::Int(i64::MAX), Number::Float(i64::MAX as f64));
+}
+
+#[test]
+fn bitwise_operators_take_integral_floats() {
+    assert_eq!(Number::Float(1.0).bit_not(), Ok(Number::Int(-2)));
+    assert_eq!(
+        Number::Float(1.5).bit_not(),
+        Err("Operand must be an integer")
+    );
+    let shifted = number::integer_op(Number::Float(1.0), Number::Int(2), number::shift_left);
+    assert_eq!(shifted, Ok(Number::Int(4)));
+    let too_far = number::integer_op(Number::Int(1), Number::Int(64), number::shift_left);
+    assert_eq!(too_far, Err("Shift amount must be between 0 and 63"));
+}
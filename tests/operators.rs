@@ -0,0 +1,148 @@
+mod common;
+
+use common::{error, output};
+use format as fmt;
+
+#[test]
+fn arithmetic_operators() {
+    let source = "
+        print(7 % 3, -7 % 3, 7.5 % 2, 2 ** 3 ** 2, -2 ** 2, 2 ** 0.5 > 1.41);
+        print(7 // 2, -7 // 2, 7.5 // 2, 1 + 2 * 3 ** 2 // 4);
+    ";
+    assert_eq!(output(source), "1 2 1.5 512 -4 true\n3 -4 3.0 5\n");
+}
+
+#[test]
+fn bitwise_operators() {
+    let source = "
+        print(6 & 3, 6 | 3, 6 ^ 3, ~6, 1 << 62, -8 >> 1);
+        print(1 | 2 ^ 3 & 4, 1 + 1 << 2, 1 < 2 == true);
+    ";
+    assert_eq!(
+        output(source),
+        "2 7 5 -7 4611686018427387904 -4\n3 8 true\n"
+    );
+}
+
+#[test]
+fn floor_division_and_comments() {
+    let source = "
+        let a = 9; // a comment
+        // a line comment // with slashes
+        print(a // 2); // 4
+        print(\"${a // 4}\");
+    ";
+    assert_eq!(output(source), "4\n2\n");
+}
+
+#[test]
+fn operator_errors() {
+    for (source, msg) in [
+        ("1.5 & 1;", "Operands must be integers"),
+        ("1 | true;", "Operands must be number"),
+        ("~1.5;", "Operand must be an integer"),
+        ("~\"a\";", "Operand must be a number"),
+        ("1 << 64;", "Shift amount must be between 0 and 63"),
+        ("1 >> -1;", "Shift amount must be between 0 and 63"),
+        ("\"a\" % 2;", "Operands must be number"),
+        ("\"a\" ** 2;", "Operands must be number"),
+        ("\"a\" // 2;", "Operands must be number"),
+    ] {
+        assert_eq!(error(source), fmt!("runtime error: {msg}"), "{source:?}");
+    }
+}
+
+#[test]
+fn compound_assignment() {
+    let source = "
+        let a = 10;
+        a += 5; a -= 3; a *= 2; a /= 4; a %= 4;
+        let s = \"a\";
+        s += \"b\";
+        print(a, s);
+    ";
+    assert_eq!(output(source), "2.0 ab\n");
+}
+
+#[test]
+fn increment_and_decrement() {
+    let source = "
+        let i = 1;
+        print(i++, i, ++i, i--, --i);
+        class C { init() { this.n = 0; } }
+        let c = C();
+        c.n++;
+        ++c.n;
+        let xs = [5];
+        xs[0]--;
+        print(c.n, xs);
+    ";
+    assert_eq!(output(source), "1 2 3 3 1\n2 [4]\n");
+}
+
+#[test]
+fn targets_are_evaluated_once() {
+    let source = "
+        let calls = 0;
+        let xs = [1, 2];
+        fn at() { calls += 1; return 1; }
+        xs[at()] += 10;
+        xs[at()]++;
+        print(xs, calls);
+    ";
+    assert_eq!(output(source), "[1, 13] 2\n");
+}
+
+#[test]
+fn update_errors() {
+    assert_eq!(
+        error("let s = \"a\";\ns -= 1;"),
+        "runtime error: Operands must be number"
+    );
+    assert_eq!(
+        error("let s = \"a\";\ns++;"),
+        "runtime error: Operand must be 'string' or 'number'"
+    );
+    assert_eq!(error("1 += 2;"), "syntax error: Invalid assignment target.");
+    assert_eq!(error("(a)++;"), "syntax error: Invalid assignment target.");
+}
+
+#[test]
+fn conditional_operator() {
+    let source = "
+        fn sign(n) { return n > 0 ? \"+\" : n < 0 ? \"-\" : \"0\"; }
+        print(sign(2), sign(-2), sign(0));
+        let x = false ? 1 : true ? 2 : 3;
+        print(x, null ? \"yes\" : \"no\");
+    ";
+    assert_eq!(output(source), "+ - 0\n2 no\n");
+}
+
+#[test]
+fn null_coalescing_only_skips_null() {
+    let source = "
+        print(null ?? 1, false ?? 1, 0 ?? 1, \"\" ?? 1, null ?? null ?? 2);
+        let m = {\"a\": null};
+        print(m[\"a\"] ?? \"default\");
+    ";
+    assert_eq!(output(source), "1 false 0  2\ndefault\n");
+}
+
+#[test]
+fn conditionals_short_circuit() {
+    let source = "
+        fn loud(x) { print(\"evaluated\", x); return x; }
+        let a = true ? loud(1) : loud(2);
+        let b = 3 ?? loud(4);
+        let c = null ?? loud(5);
+    ";
+    assert_eq!(output(source), "evaluated 1\nevaluated 5\n");
+}
+
+#[test]
+fn conditional_syntax_errors() {
+    assert_eq!(
+        error("let x = true ? 1;"),
+        "syntax error: Expected ':' after then branch of conditional"
+    );
+}
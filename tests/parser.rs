@@ -0,0 +1,68 @@
+mod common;
+
+use common::run_both;
+
+// the messages of every error reported for the source, in order
+fn errors(source: &str) -> Vec<String> {
+    let run = run_both(source);
+    assert_eq!(run.code, Some(65), "{source:?} didn't fail");
+    run.stderr
+        .lines()
+        .filter_map(|line| line.strip_prefix("syntax error: "))
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn every_syntax_error_is_reported() {
+    assert_eq!(
+        errors("let = 1;\nprint(1;\nlet y = ;\nlet z = 2"),
+        [
+            "Expected identifier",
+            "Expect ')' after arguments",
+            "Expected Expression",
+            "Expect ';' after declaration",
+        ]
+    );
+}
+
+#[test]
+fn lexer_and_parser_errors_are_reported_together() {
+    assert_eq!(
+        errors("let x = 1 € 2;\nlet y = ;\nprint(\"ok\" $);"),
+        [
+            "Unexpected character",
+            "Expect ';' after declaration",
+            "Expected Expression",
+            "Unexpected character",
+        ]
+    );
+}
+
+#[test]
+fn nothing_runs_when_an_error_is_reported() {
+    let run = run_both("print(\"ran\");\nlet x = 1 @ 2;");
+    assert_eq!(run.stdout, "");
+}
+
+#[test]
+fn recovery_resumes_at_the_next_statement() {
+    // each statement is parsed again after the missing ';' before it
+    let source = "
+        let a = 1
+        class A { m() {} }
+        let b = 2
+        fn f() {}
+        let c = 3
+        while false {}
+        let d = 4
+        for x in [] {}
+        let e = 5
+        if true {}
+        let f = 6;
+        print(a = );
+    ";
+    let mut expected = vec!["Expect ';' after declaration"; 5];
+    expected.push("Expected Expression");
+    assert_eq!(errors(source), expected);
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use common::{error, output};
+use format as fmt;
+
+#[test]
+fn functions_can_only_be_declared_in_blocks() {
+    for source in [
+        "{ if false fn f() {} else fn g() { return 1; } print(g()); }",
+        "{ let a = 1; if false fn f() {} let b = 2; print(a, b); }",
+        "while false fn f() {}",
+        "for x in [] fn f() {}",
+    ] {
+        assert_eq!(
+            error(source),
+            "syntax error: Function declarations must be inside a block",
+            "{source:?}"
+        );
+    }
+}
+
+#[test]
+fn functions_declared_in_branches_and_bodies() {
+    let source = "
+        let a = 1;
+        if a == 1 { fn f() { return a; } print(f()); }
+        for x in [2] { fn g() { return x; } print(g()); }
+        let b = 3;
+        print(a, b);
+    ";
+    assert_eq!(output(source), "1\n2\n1 3\n");
+}
+
+#[test]
+fn closures_capture_the_variable_in_scope_when_declared() {
+    let source = "
+        let a = \"global\";
+        {
+            fn show() { print(a); }
+            show();
+            let a = \"block\";
+            show();
+        }
+    ";
+    assert_eq!(output(source), "global\nglobal\n");
+}
+
+#[test]
+fn undefined_variables() {
+    assert_eq!(error("print(x);"), "runtime error: Undefined variable 'x'");
+    assert_eq!(error("x = 1;"), "runtime error: Undefined variable 'x'");
+}
+
+#[test]
+fn static_errors() {
+    for (source, msg) in [
+        (
+            "{ let a = 1; let a = 2; }",
+            "Already a variable with this name in this scope",
+        ),
+        (
+            "let a = 1;\n{ let a = a; }",
+            "Can't read local variable in its own initializer",
+        ),
+        ("return 1;", "Can't return from top-level code"),
+        (
+            "class A { init() { return 1; } }",
+            "Can't return a value from an initializer",
+        ),
+        ("print(this);", "Can't use 'this' outside of a class"),
+        (
+            "fn f() { super.m(); }",
+            "Can't use 'super' outside of a class",
+        ),
+        (
+            "class A { m() { super.m(); } }",
+            "Can't use 'super' in a class with no superclass",
+        ),
+    ] {
+        assert_eq!(error(source), fmt!("syntax error: {msg}"), "{source:?}");
+    }
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use common::run_both;
+use interp::lexer::Lexer;
+
+// the location of the first error and the source it underlines
+fn pointed(source: &str) -> (String, String) {
+    let run = run_both(source);
+    let lines: Vec<_> = run.stderr.lines().collect();
+    let location = lines[1].split_once("script.lox:").unwrap().1;
+
+    let text = lines[3].split_once("| ").unwrap().1;
+    let carets = lines[4].split_once("| ").unwrap().1;
+    let start = carets.find('^').unwrap();
+    let underlined = text.chars().skip(start).take(carets.len() - start);
+    (location.to_string(), underlined.collect())
+}
+
+#[test]
+fn tokens_have_columns_and_byte_spans() {
+    let (tokens, errors) = Lexer::new("let é =\n  \"ü\";".to_string()).scan();
+    assert!(errors.is_empty());
+    let positions: Vec<_> = tokens
+        .iter()
+        .map(|t| {
+            (
+                t.lexeme.as_str(),
+                t.line,
+                t.column,
+                t.span.start,
+                t.span.end,
+            )
+        })
+        .collect();
+    assert_eq!(
+        positions[..4],
+        [
+            ("let", 1, 1, 0, 3),
+            ("é", 1, 5, 4, 6),
+            ("=", 1, 7, 7, 8),
+            ("\"ü\"", 2, 3, 11, 15),
+        ]
+    );
+}
+
+#[test]
+fn errors_point_at_the_expression_that_failed() {
+    assert_eq!(
+        pointed("let a = 1;\nprint(a + [1].len() * (true - 1));"),
+        ("2:24".to_string(), "true - 1".to_string())
+    );
+    assert_eq!(
+        pointed("let m = {\"k\": 1};\nlet v = m[\"k\"] + m[\"no\"];"),
+        ("2:20".to_string(), "\"no\"".to_string())
+    );
+    assert_eq!(
+        pointed("class A {}\nprint(A().y.z);"),
+        ("2:11".to_string(), "y".to_string())
+    );
+    assert_eq!(
+        pointed("fn f(a) {}\nf(1,\n  2);"),
+        ("2:1".to_string(), "f(1,".to_string())
+    );
+}
+
+#[test]
+fn columns_count_characters() {
+    assert_eq!(
+        pointed("let é = \"ü\";\nprint(é, \"ü\" - é);"),
+        ("2:10".to_string(), "\"ü\" - é".to_string())
+    );
+}
+
+#[test]
+fn syntax_errors_point_at_the_token() {
+    assert_eq!(
+        pointed("let x = (1 + 2;"),
+        ("1:15".to_string(), ";".to_string())
+    );
+    assert_eq!(
+        pointed("let x = 1\nlet y = 2;"),
+        ("2:1".to_string(), "let".to_string())
+    );
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use common::{error, lex_error, output};
+
+#[test]
+fn escape_sequences() {
+    let source =
+        r#"print("tab\tquote\" backslash\\ nul\0 dollar\$ smile\u{1F600} \u{e9}\r\n", end: "");"#;
+    assert_eq!(
+        output(source),
+        "tab\tquote\" backslash\\ nul\0 dollar$ smile😀 é\r\n"
+    );
+}
+
+#[test]
+fn raw_strings_keep_backslashes() {
+    let source = r#"print(r"C:\new\table", r"${not interpolated}");"#;
+    assert_eq!(output(source), "C:\\new\\table ${not interpolated}\n");
+}
+
+#[test]
+fn triple_quoted_strings_strip_indentation() {
+    let source = "
+        let s = \"\"\"
+            first
+              indented
+            \"quoted\" \\t
+            \"\"\";
+        print(s, end: \"\");
+        print(\"\"\"one line\"\"\");
+    ";
+    assert_eq!(output(source), "first\n  indented\n\"quoted\" \tone line\n");
+}
+
+#[test]
+fn escape_errors() {
+    for source in [r#""\q""#, r#""\x41""#] {
+        assert_eq!(lex_error(source), "Invalid escape sequence", "{source:?}");
+    }
+    for source in [r#""\u{110000}""#, r#""\u{D800}""#, r#""\u{}""#, r#""\u41""#] {
+        assert_eq!(lex_error(source), "Invalid unicode escape", "{source:?}");
+    }
+}
+
+#[test]
+fn unterminated_strings() {
+    for source in [r#""\""#, "\"\"\"never closed\"", "r\"never closed"] {
+        assert_eq!(lex_error(source), "Unterminated string", "{source:?}");
+    }
+}
+
+#[test]
+fn interpolation_formats_any_value() {
+    let source = "
+        let n = 3;
+        let xs = [1, \"a\"];
+        print(\"n=${n}, half=${n / 2}, xs=${xs}, none=${null}, ${n > 2}\");
+        print(\"${n}${n}\", \"${\"nested ${n + 1}\"}\", \"${ {\"k\": 1}[\"k\"] }\");
+        print(\"before ${fn () { return \"}\"; }()} after\");
+    ";
+    assert_eq!(
+        output(source),
+        "n=3, half=1.5, xs=[1, a], none=null, true\n33 nested 4 1\nbefore } after\n"
+    );
+}
+
+#[test]
+fn interpolated_expressions_run_in_order() {
+    let source = "
+        let i = 0;
+        fn next() { i += 1; return i; }
+        print(\"${next()} ${next()} ${next()}\");
+    ";
+    assert_eq!(output(source), "1 2 3\n");
+}
+
+#[test]
+fn interpolation_errors() {
+    assert_eq!(
+        error("print(\"${1 2}\");"),
+        "syntax error: Expected '}' after interpolated expression"
+    );
+    assert_eq!(
+        error("print(\"${}\");"),
+        "syntax error: Expected Expression"
+    );
+    assert_eq!(
+        error("print(\"${missing}\");"),
+        "runtime error: Undefined variable 'missing'"
+    );
+    assert_eq!(lex_error("\"${1 + 2\""), "Unterminated string");
+}